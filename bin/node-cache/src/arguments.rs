@@ -1,8 +1,14 @@
-use clap::Parser;
+use alloy_primitives::Address;
+use clap::{Parser, Subcommand};
+use node_cache_recorder::EvictionPolicy;
+use node_cache_rpc::{Finality, MethodTtl, SubmissionPolicy};
 
 #[derive(Parser, Debug)]
 #[command(name="node-cache", version, about, long_about = None)]
 pub struct AppArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(long, default_value = "127.0.0.1:7777")]
     pub host: String,
 
@@ -16,4 +22,78 @@ pub struct AppArgs {
     // Path to the database fil
     #[arg(long)]
     pub db_file_path: String,
+
+    /// Seed `eth_getStorageAt` entries from `debug_storageRangeAt` results
+    #[arg(long, default_value = "false")]
+    pub seed_storage: bool,
+
+    /// What to do with sent transactions: forward, reject or mock
+    #[arg(long, default_value = "reject")]
+    pub submission_policy: SubmissionPolicy,
+
+    /// Search cached blocks for `eth_getTransactionBySenderAndNonce` if the node does not support it
+    #[arg(long, default_value = "false")]
+    pub sender_nonce_fallback: bool,
+
+    /// Accounts returned by `eth_accounts` without a node
+    #[arg(long, value_delimiter = ',')]
+    pub accounts: Vec<Address>,
+
+    /// Coinbase returned by `eth_coinbase` without a node
+    #[arg(long)]
+    pub author: Option<Address>,
+
+    /// Protocol version returned by `eth_protocolVersion` without a node
+    #[arg(long)]
+    pub protocol_version: Option<u64>,
+
+    /// Only record blocks that are finalized or have this many confirmations
+    #[arg(long)]
+    pub finality: Option<Finality>,
+
+    /// Treat this block as the latest block and reject newer blocks
+    #[arg(long)]
+    pub fork_block: Option<u64>,
+
+    /// Seconds between two blocks of the node, gas prices and the finalized block are reused for that long
+    #[arg(long, default_value = "12")]
+    pub block_time: u64,
+
+    /// Record `eth_blockNumber` and fee methods in call order and replay them in the same order without a node
+    #[arg(long, default_value = "false")]
+    pub record_sequences: bool,
+
+    /// Answer balance, nonce, code and storage requests from other blocks using recorded state diffs
+    #[arg(long, default_value = "false")]
+    pub derive_state: bool,
+
+    /// Store the bytecode of existing `eth_getCode` entries once by code hash before starting
+    #[arg(long, default_value = "false")]
+    pub dedup_code: bool,
+
+    /// Verify blocks, transactions, receipts and state against block hashes and roots before recording
+    #[arg(long, default_value = "false")]
+    pub verify: bool,
+
+    /// Print the recorded value and provenance of this key and exit
+    #[arg(long)]
+    pub inspect: Option<String>,
+
+    /// Expire the entries of an RPC method after the given seconds, e.g. `eth_getBalance=60,eth_gasPrice=10`
+    #[arg(long, value_delimiter = ',')]
+    pub ttl: Vec<MethodTtl>,
+
+    /// Maximum size of the database file in bytes, entries are evicted once it is exceeded
+    #[arg(long)]
+    pub max_db_size: Option<u64>,
+
+    /// Which entries to evict first when the database is full: lru or lfu
+    #[arg(long, default_value = "lru")]
+    pub eviction: EvictionPolicy,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Upgrade the database to the current key schema and exit
+    Migrate,
 }
@@ -0,0 +1,585 @@
+use crate::{Provenance, Recorder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Entry to drop first once the database is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Least recently used
+    #[default]
+    Lru,
+    /// Least frequently used, the least recently used of them on a tie
+    Lfu,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lru" => Ok(EvictionPolicy::Lru),
+            "lfu" => Ok(EvictionPolicy::Lfu),
+            _ => Err(format!("Unknown eviction policy: {}, expected lru or lfu", s)),
+        }
+    }
+}
+
+/// Time-to-live of all entries whose key starts with the prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTtl {
+    pub prefix: String,
+    pub ttl: Duration,
+}
+
+impl FromStr for KeyTtl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((prefix, seconds)) = s.split_once('=') else {
+            return Err(format!("Invalid ttl: {}, expected <key prefix>=<seconds>", s));
+        };
+        let seconds: u64 = seconds.parse().map_err(|_| format!("Invalid ttl seconds: {}", seconds))?;
+        Ok(KeyTtl { prefix: prefix.to_string(), ttl: Duration::from_secs(seconds) })
+    }
+}
+
+/// How an entry depends on another entry, so eviction never leaves a dangling reference behind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryLink {
+    /// Never evicted to make room, e.g. an index which keeps other entries consistent
+    Pinned,
+    /// Only evicted together with the target, e.g. a pointer to the target or an index of it
+    EvictedWith(String),
+    /// The target is not evicted as long as the entry refers to it, e.g. shared bytecode
+    RefersTo(String),
+}
+
+/// Links of a recorded entry, derived from its key and value
+pub type EntryLinks = fn(&str, &serde_json::Value) -> Option<EntryLink>;
+
+fn no_links(_key: &str, _value: &serde_json::Value) -> Option<EntryLink> {
+    None
+}
+
+#[derive(Debug, Clone)]
+pub struct EvictionConfig {
+    /// Entries without a matching prefix never expire
+    pub ttls: Vec<KeyTtl>,
+    /// Prefixes of all kinds of keys. The longest known prefix of a key decides its kind, so a ttl of `debug_trace_call`
+    /// does not apply to `debug_trace_call_many` entries.
+    pub key_prefixes: Vec<String>,
+    /// Maximum size of the database in bytes, counting keys, values and provenance. Unbounded if not set.
+    pub max_size: Option<u64>,
+    pub policy: EvictionPolicy,
+    pub links: EntryLinks,
+}
+
+impl Default for EvictionConfig {
+    fn default() -> Self {
+        EvictionConfig { ttls: vec![], key_prefixes: vec![], max_size: None, policy: EvictionPolicy::default(), links: no_links }
+    }
+}
+
+impl EvictionConfig {
+    /// Without ttls and a maximum size nothing is ever evicted
+    pub fn is_active(&self) -> bool {
+        !self.ttls.is_empty() || self.max_size.is_some()
+    }
+
+    fn ttl(&self, key: &str) -> Option<Duration> {
+        let prefix = self
+            .key_prefixes
+            .iter()
+            .chain(self.ttls.iter().map(|ttl| &ttl.prefix))
+            .filter(|prefix| key.starts_with(prefix.as_str()))
+            .max_by_key(|prefix| prefix.len())?;
+        self.ttls.iter().filter(|ttl| &ttl.prefix == prefix).map(|ttl| ttl.ttl).min()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EntryUsage {
+    /// Unix timestamp in seconds, entries without provenance count from the start
+    recorded_at: u64,
+    /// Tick of the usage clock at the last access
+    last_access: u64,
+    hits: u64,
+    size: u64,
+}
+
+/// Entries ordered by the eviction policy, the first entry is evicted first
+type EvictionOrder = BTreeSet<((u64, u64), String)>;
+
+#[derive(Debug, Default)]
+struct Usage {
+    entries: HashMap<String, EntryUsage>,
+    /// Only entries which can be evicted on their own
+    order: EvictionOrder,
+    links: HashMap<String, EntryLink>,
+    /// Entries evicted together with the target
+    dependents: HashMap<String, BTreeSet<String>>,
+    /// Number of entries referring to the target
+    referrers: HashMap<String, usize>,
+    policy: EvictionPolicy,
+    /// Increased on every access, orders accesses without depending on the resolution of the system clock
+    clock: u64,
+    size: u64,
+}
+
+impl Usage {
+    fn new(policy: EvictionPolicy) -> Self {
+        Usage { policy, ..Usage::default() }
+    }
+
+    fn rank(&self, usage: &EntryUsage) -> (u64, u64) {
+        match self.policy {
+            EvictionPolicy::Lru => (0, usage.last_access),
+            EvictionPolicy::Lfu => (usage.hits, usage.last_access),
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn is_evictable(&self, key: &str) -> bool {
+        !matches!(self.links.get(key), Some(EntryLink::Pinned | EntryLink::EvictedWith(_))) && !self.referrers.contains_key(key)
+    }
+
+    /// Put the entry into the eviction order if it can be evicted on its own
+    fn order_entry(&mut self, key: &str) {
+        if let Some(usage) = self.entries.get(key).copied() {
+            if self.is_evictable(key) {
+                self.order.insert((self.rank(&usage), key.to_string()));
+            }
+        }
+    }
+
+    /// A new recording of the key starts without hits, entries evicted with the key stay linked to it
+    fn insert(&mut self, key: &str, recorded_at: u64, size: u64, link: Option<EntryLink>) {
+        self.forget(key);
+        let usage = EntryUsage { recorded_at, last_access: self.tick(), hits: 0, size };
+        self.entries.insert(key.to_string(), usage);
+        self.size += size;
+        match link {
+            Some(EntryLink::EvictedWith(ref target)) => {
+                self.dependents.entry(target.clone()).or_default().insert(key.to_string());
+            }
+            Some(EntryLink::RefersTo(ref target)) => {
+                *self.referrers.entry(target.clone()).or_default() += 1;
+                if let Some(target_usage) = self.entries.get(target).copied() {
+                    self.order.remove(&(self.rank(&target_usage), target.clone()));
+                }
+            }
+            Some(EntryLink::Pinned) | None => {}
+        }
+        if let Some(link) = link {
+            self.links.insert(key.to_string(), link);
+        }
+        self.order_entry(key);
+    }
+
+    fn touch(&mut self, key: &str) {
+        let last_access = self.tick();
+        let Some(mut usage) = self.entries.get(key).copied() else {
+            return;
+        };
+        self.order.remove(&(self.rank(&usage), key.to_string()));
+        usage.last_access = last_access;
+        usage.hits += 1;
+        self.entries.insert(key.to_string(), usage);
+        self.order_entry(key);
+    }
+
+    /// Stop tracking the entry and release its link to the target
+    fn forget(&mut self, key: &str) {
+        if let Some(previous) = self.entries.remove(key) {
+            self.order.remove(&(self.rank(&previous), key.to_string()));
+            self.size -= previous.size;
+        }
+        match self.links.remove(key) {
+            Some(EntryLink::EvictedWith(target)) => {
+                if let Some(dependents) = self.dependents.get_mut(&target) {
+                    dependents.remove(key);
+                    if dependents.is_empty() {
+                        self.dependents.remove(&target);
+                    }
+                }
+            }
+            Some(EntryLink::RefersTo(target)) => {
+                if let Some(referrers) = self.referrers.get_mut(&target) {
+                    *referrers -= 1;
+                    if *referrers == 0 {
+                        self.referrers.remove(&target);
+                        self.order_entry(&target);
+                    }
+                }
+            }
+            Some(EntryLink::Pinned) | None => {}
+        }
+    }
+
+    /// Stop tracking the entry and all entries evicted together with it, returns the keys to remove
+    fn evict(&mut self, key: &str) -> Vec<String> {
+        let mut evicted = vec![];
+        let mut pending = vec![key.to_string()];
+        while let Some(key) = pending.pop() {
+            pending.extend(self.dependents.remove(&key).into_iter().flatten());
+            self.forget(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+
+    /// Entry to evict first, other than the kept key and the entry it is evicted with
+    fn victim(&self, keep: &str) -> Option<String> {
+        let kept_with = match self.links.get(keep) {
+            Some(EntryLink::EvictedWith(target)) => Some(target.as_str()),
+            _ => None,
+        };
+        self.order.iter().map(|(_, key)| key).find(|key| key.as_str() != keep && Some(key.as_str()) != kept_with).cloned()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default()
+}
+
+/// Wraps any recorder to expire entries after their time-to-live and keep the database below a maximum size
+pub struct EvictingRecorder<R: Recorder> {
+    inner: R,
+    config: EvictionConfig,
+    usage: Mutex<Usage>,
+}
+
+impl<R: Recorder + Sync> EvictingRecorder<R> {
+    /// Collects the age, size and links of the existing entries, only as far as the config needs them
+    pub async fn new(inner: R, config: EvictionConfig) -> eyre::Result<Self> {
+        let mut usage = Usage::new(config.policy);
+        if config.is_active() {
+            let started = now();
+            for key in inner.keys().await? {
+                let recorded_at = match config.ttl(&key) {
+                    Some(_) => inner.provenance(&key).await?.map(|provenance| provenance.recorded_at).unwrap_or(started),
+                    None => started,
+                };
+                let size = match config.max_size {
+                    Some(_) => inner.stored_size(&key).await?,
+                    None => 0,
+                };
+                let link = match inner.peek::<serde_json::Value>(&key).await? {
+                    Some(value) => (config.links)(&key, &value),
+                    None => None,
+                };
+                usage.insert(&key, recorded_at, size, link);
+            }
+        }
+        Ok(EvictingRecorder { inner, config, usage: Mutex::new(usage) })
+    }
+
+    fn is_expired(&self, key: &str, usage: &EntryUsage) -> bool {
+        self.config.ttl(key).is_some_and(|ttl| now().saturating_sub(usage.recorded_at) >= ttl.as_secs())
+    }
+
+    /// Keys to remove until the database fits again, the just recorded key is kept
+    fn victims(&self, recorded: &str) -> Vec<String> {
+        let Some(max_size) = self.config.max_size else {
+            return vec![];
+        };
+        let mut usage = self.usage.lock().unwrap();
+        let mut victims = vec![];
+        while usage.size > max_size {
+            let Some(victim) = usage.victim(recorded) else {
+                break;
+            };
+            victims.extend(usage.evict(&victim));
+        }
+        victims
+    }
+
+    async fn track<T: Serialize + Send + Sync>(&self, key: &str, value: &T, recorded_at: u64) -> eyre::Result<()> {
+        if !self.config.is_active() {
+            return Ok(());
+        }
+        let size = if self.config.max_size.is_some() { self.inner.stored_size(key).await? } else { 0 };
+        let link = (self.config.links)(key, &serde_json::to_value(value)?);
+        self.usage.lock().unwrap().insert(key, recorded_at, size, link);
+        for victim in self.victims(key) {
+            self.inner.remove(&victim).await?;
+        }
+        Ok(())
+    }
+
+    fn is_expired_key(&self, key: &str) -> bool {
+        let usage = self.usage.lock().unwrap();
+        usage.entries.get(key).is_some_and(|entry| self.is_expired(key, entry))
+    }
+}
+
+#[async_trait::async_trait]
+impl<R: Recorder + Send + Sync> Recorder for EvictingRecorder<R> {
+    async fn record<T: Serialize + Send + Sync>(&self, key: &str, value: &T) -> eyre::Result<()> {
+        self.inner.record(key, value).await?;
+        self.track(key, value, now()).await
+    }
+
+    async fn record_with_provenance<T: Serialize + Send + Sync>(&self, key: &str, value: &T, provenance: &Provenance) -> eyre::Result<()> {
+        self.inner.record_with_provenance(key, value, provenance).await?;
+        self.track(key, value, provenance.recorded_at).await
+    }
+
+    async fn provenance(&self, key: &str) -> eyre::Result<Option<Provenance>> {
+        self.inner.provenance(key).await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>> {
+        if !self.config.is_active() {
+            return self.inner.get(key).await;
+        }
+        let expired = {
+            let mut usage = self.usage.lock().unwrap();
+            let expired = usage.entries.get(key).is_some_and(|entry| self.is_expired(key, entry));
+            if expired {
+                usage.evict(key)
+            } else {
+                usage.touch(key);
+                vec![]
+            }
+        };
+        if !expired.is_empty() {
+            for key in expired.iter() {
+                self.inner.remove(key).await?;
+            }
+            return Ok(None);
+        }
+        self.inner.get(key).await
+    }
+
+    /// An expired entry is not returned, but it is only removed once it is used
+    async fn peek<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>> {
+        if self.config.is_active() && self.is_expired_key(key) {
+            return Ok(None);
+        }
+        self.inner.peek(key).await
+    }
+
+    async fn keys(&self) -> eyre::Result<Vec<String>> {
+        self.inner.keys().await
+    }
+
+    async fn remove(&self, key: &str) -> eyre::Result<()> {
+        self.usage.lock().unwrap().forget(key);
+        self.inner.remove(key).await
+    }
+
+    async fn stored_size(&self, key: &str) -> eyre::Result<u64> {
+        self.inner.stored_size(key).await
+    }
+
+    async fn save(&self) -> eyre::Result<()> {
+        self.inner.save().await
+    }
+
+    async fn schema_version(&self) -> eyre::Result<Option<u32>> {
+        self.inner.schema_version().await
+    }
+
+    async fn set_schema_version(&self, version: u32) -> eyre::Result<()> {
+        self.inner.set_schema_version(version).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryRecorder;
+
+    async fn evicting(config: EvictionConfig) -> EvictingRecorder<MemoryRecorder> {
+        EvictingRecorder::new(MemoryRecorder::new(), config).await.unwrap()
+    }
+
+    fn provenance(recorded_at: u64) -> Provenance {
+        Provenance { recorded_at, ..Provenance::default() }
+    }
+
+    #[tokio::test]
+    async fn lru_evicts_least_recently_used() {
+        let recorder = evicting(EvictionConfig { max_size: Some(4), policy: EvictionPolicy::Lru, ..EvictionConfig::default() }).await;
+        recorder.record("a", &1).await.unwrap();
+        recorder.record("b", &2).await.unwrap();
+        for _ in 0..3 {
+            recorder.get::<u64>("b").await.unwrap();
+        }
+        recorder.get::<u64>("a").await.unwrap();
+        recorder.record("c", &3).await.unwrap();
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), Some(1));
+        assert_eq!(recorder.get::<u64>("b").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("c").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn lfu_evicts_least_frequently_used() {
+        let recorder = evicting(EvictionConfig { max_size: Some(4), policy: EvictionPolicy::Lfu, ..EvictionConfig::default() }).await;
+        recorder.record("a", &1).await.unwrap();
+        recorder.record("b", &2).await.unwrap();
+        for _ in 0..3 {
+            recorder.get::<u64>("b").await.unwrap();
+        }
+        recorder.get::<u64>("a").await.unwrap();
+        recorder.record("c", &3).await.unwrap();
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("b").await.unwrap(), Some(2));
+        assert_eq!(recorder.get::<u64>("c").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_recorded_entry() {
+        let recorder = evicting(EvictionConfig { max_size: Some(1), ..EvictionConfig::default() }).await;
+        recorder.record("a", &1).await.unwrap();
+        recorder.record("b", &22).await.unwrap();
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("b").await.unwrap(), Some(22));
+    }
+
+    #[tokio::test]
+    async fn peeking_does_not_count_as_use() {
+        let recorder = evicting(EvictionConfig { max_size: Some(4), ..EvictionConfig::default() }).await;
+        recorder.record("a", &1).await.unwrap();
+        recorder.record("b", &2).await.unwrap();
+        for _ in 0..3 {
+            recorder.peek::<u64>("a").await.unwrap();
+        }
+        recorder.record("c", &3).await.unwrap();
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn counts_keys_and_provenance() {
+        let recorder = evicting(EvictionConfig { max_size: Some(1000), ..EvictionConfig::default() }).await;
+        recorder.record_with_provenance("get_balance", &1, &provenance(1)).await.unwrap();
+        let size = "get_balance".len() + 1 + serde_json::to_vec(&provenance(1)).unwrap().len();
+        assert_eq!(recorder.usage.lock().unwrap().size, size as u64);
+    }
+
+    /// Bytecode is referred to by code entries, pointers and index entries only exist with their target
+    fn links(key: &str, value: &serde_json::Value) -> Option<EntryLink> {
+        match key.split_once(':') {
+            Some(("code_at", _)) => value.as_str().map(|code_key| EntryLink::RefersTo(code_key.to_string())),
+            Some(("pointer", _)) => value.as_str().map(|target| EntryLink::EvictedWith(target.to_string())),
+            Some(("index", _)) => Some(EntryLink::Pinned),
+            _ => None,
+        }
+    }
+
+    async fn linked(max_size: u64) -> EvictingRecorder<MemoryRecorder> {
+        evicting(EvictionConfig { max_size: Some(max_size), links, ..EvictionConfig::default() }).await
+    }
+
+    #[tokio::test]
+    async fn code_outlives_the_entries_referring_to_it() {
+        // 12 bytes for the code and 17 bytes for each entry referring to it
+        let recorder = linked(50).await;
+        recorder.record("code:1", &"6000").await.unwrap();
+        recorder.record("code_at:a", &"code:1").await.unwrap();
+        recorder.record("code_at:b", &"code:1").await.unwrap();
+
+        // the code is the oldest entry, but still referred to
+        recorder.record("other:1", &1).await.unwrap();
+        assert_eq!(recorder.get::<String>("code_at:a").await.unwrap(), None);
+        assert_eq!(recorder.peek::<String>("code:1").await.unwrap(), Some("6000".to_string()));
+
+        recorder.record("other:2", &2).await.unwrap();
+        recorder.record("other:3", &3).await.unwrap();
+        assert_eq!(recorder.get::<String>("code_at:b").await.unwrap(), None);
+        assert_eq!(recorder.peek::<String>("code:1").await.unwrap(), Some("6000".to_string()));
+
+        // without referring entries the code is evicted like any other entry
+        recorder.record("other:4", &4).await.unwrap();
+        recorder.record("other:5", &5).await.unwrap();
+        assert_eq!(recorder.get::<String>("code:1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn evicts_pointers_together_with_their_target() {
+        let recorder = linked(40).await;
+        recorder.record("block:1", &"header").await.unwrap();
+        recorder.record("pointer:1", &"block:1").await.unwrap();
+        recorder.record("pointer:latest", &"block:1").await.unwrap();
+        recorder.record("other:1", &1).await.unwrap();
+        assert_eq!(recorder.get::<String>("block:1").await.unwrap(), None);
+        assert_eq!(recorder.get::<String>("pointer:1").await.unwrap(), None);
+        assert_eq!(recorder.get::<String>("pointer:latest").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("other:1").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn never_evicts_pinned_entries() {
+        let recorder = linked(15).await;
+        recorder.record("index:1", &["a"]).await.unwrap();
+        recorder.record("a", &1).await.unwrap();
+        recorder.record("b", &2).await.unwrap();
+        assert_eq!(recorder.get::<Vec<String>>("index:1").await.unwrap(), Some(vec!["a".to_string()]));
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn expires_after_ttl() {
+        let ttls = vec![KeyTtl { prefix: "get_balance".to_string(), ttl: Duration::from_secs(60) }];
+        let recorder = evicting(EvictionConfig { ttls, ..EvictionConfig::default() }).await;
+        recorder.record_with_provenance("get_balance_old", &1, &provenance(now() - 120)).await.unwrap();
+        recorder.record_with_provenance("get_balance_new", &2, &provenance(now())).await.unwrap();
+        recorder.record_with_provenance("chain_id", &3, &provenance(now() - 120)).await.unwrap();
+        assert_eq!(recorder.get::<u64>("get_balance_old").await.unwrap(), None);
+        assert_eq!(recorder.inner.get::<u64>("get_balance_old").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("get_balance_new").await.unwrap(), Some(2));
+        assert_eq!(recorder.get::<u64>("chain_id").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn expires_existing_entries_by_provenance() {
+        let inner = MemoryRecorder::new();
+        inner.record_with_provenance("get_balance_old", &1, &provenance(now() - 120)).await.unwrap();
+        let ttls = vec![KeyTtl { prefix: "get_balance".to_string(), ttl: Duration::from_secs(60) }];
+        let recorder = EvictingRecorder::new(inner, EvictionConfig { ttls, ..EvictionConfig::default() }).await.unwrap();
+        assert_eq!(recorder.get::<u64>("get_balance_old").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn ttl_applies_to_the_longest_known_prefix() {
+        let ttls = vec![KeyTtl { prefix: "trace_call".to_string(), ttl: Duration::ZERO }];
+        let key_prefixes = vec!["trace_call".to_string(), "trace_call_many".to_string()];
+        let recorder = evicting(EvictionConfig { ttls, key_prefixes, ..EvictionConfig::default() }).await;
+        recorder.record("trace_call1", &1).await.unwrap();
+        recorder.record("trace_call_many1", &2).await.unwrap();
+        assert_eq!(recorder.get::<u64>("trace_call1").await.unwrap(), None);
+        assert_eq!(recorder.get::<u64>("trace_call_many1").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn inactive_config_tracks_nothing() {
+        let recorder = evicting(EvictionConfig::default()).await;
+        recorder.record("a", &1).await.unwrap();
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), Some(1));
+        assert!(recorder.usage.lock().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn parses_key_ttl() {
+        assert_eq!("get_balance=60".parse::<KeyTtl>(), Ok(KeyTtl { prefix: "get_balance".to_string(), ttl: Duration::from_secs(60) }));
+        assert!("get_balance".parse::<KeyTtl>().is_err());
+        assert!("get_balance=soon".parse::<KeyTtl>().is_err());
+    }
+
+    #[test]
+    fn parses_eviction_policy() {
+        assert_eq!("lru".parse::<EvictionPolicy>(), Ok(EvictionPolicy::Lru));
+        assert_eq!("lfu".parse::<EvictionPolicy>(), Ok(EvictionPolicy::Lfu));
+        assert!("fifo".parse::<EvictionPolicy>().is_err());
+    }
+}
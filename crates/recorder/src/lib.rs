@@ -1,5 +1,11 @@
+mod evicting_recorder;
+mod memory_recorder;
 mod pickle_recorder;
+mod provenance;
 mod recorder;
 
+pub use evicting_recorder::{EntryLink, EntryLinks, EvictingRecorder, EvictionConfig, EvictionPolicy, KeyTtl};
+pub use memory_recorder::MemoryRecorder;
 pub use pickle_recorder::PickleRecorder;
+pub use provenance::Provenance;
 pub use recorder::Recorder;
@@ -0,0 +1,93 @@
+use crate::{Provenance, Recorder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+#[derive(Debug, Default)]
+struct Entries {
+    values: HashMap<String, serde_json::Value>,
+    provenances: HashMap<String, Provenance>,
+    schema_version: Option<u32>,
+}
+
+/// Keeps all records in memory and never writes a file, e.g. for tests
+#[derive(Debug, Default)]
+pub struct MemoryRecorder {
+    entries: RwLock<Entries>,
+}
+
+impl MemoryRecorder {
+    pub fn new() -> Self {
+        MemoryRecorder::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl Recorder for MemoryRecorder {
+    async fn record<T: Serialize + Send + Sync>(&self, key: &str, value: &T) -> eyre::Result<()> {
+        let mut entries = self.entries.write().await;
+        entries.values.insert(key.to_string(), serde_json::to_value(value)?);
+        // the provenance of a previous recording does not apply anymore
+        entries.provenances.remove(key);
+        Ok(())
+    }
+
+    async fn record_with_provenance<T: Serialize + Send + Sync>(&self, key: &str, value: &T, provenance: &Provenance) -> eyre::Result<()> {
+        let mut entries = self.entries.write().await;
+        entries.values.insert(key.to_string(), serde_json::to_value(value)?);
+        entries.provenances.insert(key.to_string(), provenance.clone());
+        Ok(())
+    }
+
+    async fn provenance(&self, key: &str) -> eyre::Result<Option<Provenance>> {
+        Ok(self.entries.read().await.provenances.get(key).cloned())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>> {
+        match self.entries.read().await.values.get(key) {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn peek<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>> {
+        self.get(key).await
+    }
+
+    async fn keys(&self) -> eyre::Result<Vec<String>> {
+        Ok(self.entries.read().await.values.keys().cloned().collect())
+    }
+
+    async fn remove(&self, key: &str) -> eyre::Result<()> {
+        let mut entries = self.entries.write().await;
+        entries.values.remove(key);
+        entries.provenances.remove(key);
+        Ok(())
+    }
+
+    async fn stored_size(&self, key: &str) -> eyre::Result<u64> {
+        let entries = self.entries.read().await;
+        let Some(value) = entries.values.get(key) else {
+            return Ok(0);
+        };
+        let provenance = match entries.provenances.get(key) {
+            Some(provenance) => serde_json::to_vec(provenance)?.len(),
+            None => 0,
+        };
+        Ok((key.len() + serde_json::to_vec(value)?.len() + provenance) as u64)
+    }
+
+    async fn save(&self) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    async fn schema_version(&self) -> eyre::Result<Option<u32>> {
+        Ok(self.entries.read().await.schema_version)
+    }
+
+    async fn set_schema_version(&self, version: u32) -> eyre::Result<()> {
+        self.entries.write().await.schema_version = Some(version);
+        Ok(())
+    }
+}
@@ -1,31 +1,234 @@
-use crate::Recorder;
+use crate::{Provenance, Recorder};
 use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
 use tokio::sync::RwLock;
 
+/// Provenance is stored next to the entry under the key with this prefix
+const PROVENANCE_KEY_PREFIX: &str = "provenance:";
+
+/// The part of the provenance shared by all entries of a session is stored once under this prefix and its id
+const SESSION_KEY_PREFIX: &str = "provenance_session:";
+
+/// Header of the database, never returned as a recorded key
+const SCHEMA_VERSION_KEY: &str = "header:key_schema_version";
+
+fn provenance_key(key: &str) -> String {
+    format!("{}{}", PROVENANCE_KEY_PREFIX, key)
+}
+
+fn session_key(id: u32) -> String {
+    format!("{}{}", SESSION_KEY_PREFIX, id)
+}
+
+/// The file is a JSON object of the keys and their serialized values as JSON strings
+fn persisted_size(key: &str, value: &serde_json::Value) -> eyre::Result<u64> {
+    let value = serde_json::to_string(value)?;
+    // colon and comma between the entries
+    Ok((serde_json::to_string(key)?.len() + serde_json::to_string(&value)?.len() + 2) as u64)
+}
+
+/// Provenance fields which are the same for every entry recorded by one node cache run
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct Session {
+    upstream: Option<String>,
+    client_version: Option<String>,
+    node_cache_version: String,
+    key_schema_version: u32,
+}
+
+impl From<&Provenance> for Session {
+    fn from(provenance: &Provenance) -> Self {
+        Session {
+            upstream: provenance.upstream.clone(),
+            client_version: provenance.client_version.clone(),
+            node_cache_version: provenance.node_cache_version.clone(),
+            key_schema_version: provenance.key_schema_version,
+        }
+    }
+}
+
+/// Provenance of one entry: session id, recorded at and latency. Serialized as a tuple to keep small entries small.
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryProvenance(u32, u64, Option<u64>);
+
 pub struct PickleRecorder {
     db: RwLock<PickleDb>,
+    /// Ids of the sessions stored in the database
+    sessions: Mutex<HashMap<Session, u32>>,
 }
 
 impl PickleRecorder {
-    pub fn new(db_path: String) -> Self {
-        let db = PickleDb::new(db_path, PickleDbDumpPolicy::DumpUponRequest, SerializationMethod::Json);
-        PickleRecorder { db: RwLock::new(db) }
+    /// Continues with the records of an existing file
+    pub fn new(db_path: String) -> eyre::Result<Self> {
+        let db = if Path::new(&db_path).exists() {
+            PickleDb::load(&db_path, PickleDbDumpPolicy::DumpUponRequest, SerializationMethod::Json)?
+        } else {
+            PickleDb::new(db_path, PickleDbDumpPolicy::DumpUponRequest, SerializationMethod::Json)
+        };
+        let mut sessions = HashMap::new();
+        for key in db.get_all() {
+            let Some(id) = key.strip_prefix(SESSION_KEY_PREFIX).and_then(|id| id.parse().ok()) else {
+                continue;
+            };
+            if let Some(session) = db.get::<Session>(&key) {
+                sessions.insert(session, id);
+            }
+        }
+        Ok(PickleRecorder { db: RwLock::new(db), sessions: Mutex::new(sessions) })
+    }
+
+    /// Id of the session of the provenance, a new session is stored first
+    fn session_id(&self, db: &mut PickleDb, provenance: &Provenance) -> eyre::Result<u32> {
+        let session = Session::from(provenance);
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(id) = sessions.get(&session) {
+            return Ok(*id);
+        }
+        let id = sessions.values().max().map(|id| id + 1).unwrap_or_default();
+        db.set(&session_key(id), &session)?;
+        sessions.insert(session, id);
+        Ok(id)
     }
 }
 //tokio::task::spawn_blocking(move || {
 #[async_trait::async_trait]
 impl Recorder for PickleRecorder {
     async fn record<T: Serialize + Send + Sync>(&self, key: &str, value: &T) -> eyre::Result<()> {
-        Ok(self.db.write().await.set(key, &value)?)
+        let mut db = self.db.write().await;
+        db.set(key, &value)?;
+        // the provenance of a previous recording does not apply anymore
+        db.rem(&provenance_key(key))?;
+        Ok(())
+    }
+
+    async fn record_with_provenance<T: Serialize + Send + Sync>(&self, key: &str, value: &T, provenance: &Provenance) -> eyre::Result<()> {
+        let mut db = self.db.write().await;
+        let session = self.session_id(&mut db, provenance)?;
+        db.set(key, &value)?;
+        Ok(db.set(&provenance_key(key), &EntryProvenance(session, provenance.recorded_at, provenance.latency_ms))?)
+    }
+
+    async fn provenance(&self, key: &str) -> eyre::Result<Option<Provenance>> {
+        let db = self.db.read().await;
+        let Some(EntryProvenance(session, recorded_at, latency_ms)) = db.get::<EntryProvenance>(&provenance_key(key)) else {
+            return Ok(None);
+        };
+        let Some(session) = db.get::<Session>(&session_key(session)) else {
+            eyre::bail!("Provenance of {} refers to the unknown session {}", key, session);
+        };
+        Ok(Some(Provenance {
+            recorded_at,
+            upstream: session.upstream,
+            client_version: session.client_version,
+            latency_ms,
+            node_cache_version: session.node_cache_version,
+            key_schema_version: session.key_schema_version,
+        }))
     }
 
     async fn get<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>> {
         Ok(self.db.read().await.get::<T>(key))
     }
 
+    async fn peek<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>> {
+        self.get(key).await
+    }
+
+    async fn keys(&self) -> eyre::Result<Vec<String>> {
+        Ok(self
+            .db
+            .read()
+            .await
+            .get_all()
+            .into_iter()
+            .filter(|key| !key.starts_with(PROVENANCE_KEY_PREFIX) && !key.starts_with(SESSION_KEY_PREFIX) && key != SCHEMA_VERSION_KEY)
+            .collect())
+    }
+
+    async fn remove(&self, key: &str) -> eyre::Result<()> {
+        let mut db = self.db.write().await;
+        db.rem(key)?;
+        db.rem(&provenance_key(key))?;
+        Ok(())
+    }
+
+    async fn stored_size(&self, key: &str) -> eyre::Result<u64> {
+        let db = self.db.read().await;
+        let mut size = 0;
+        for key in [key.to_string(), provenance_key(key)] {
+            if let Some(value) = db.get::<serde_json::Value>(&key) {
+                size += persisted_size(&key, &value)?;
+            }
+        }
+        Ok(size)
+    }
+
     async fn save(&self) -> eyre::Result<()> {
         Ok(self.db.write().await.dump()?)
     }
+
+    async fn schema_version(&self) -> eyre::Result<Option<u32>> {
+        Ok(self.db.read().await.get::<u32>(SCHEMA_VERSION_KEY))
+    }
+
+    async fn set_schema_version(&self, version: u32) -> eyre::Result<()> {
+        Ok(self.db.write().await.set(SCHEMA_VERSION_KEY, &version)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("node-cache-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().to_string()
+    }
+
+    fn provenance(recorded_at: u64, upstream: &str) -> Provenance {
+        Provenance {
+            recorded_at,
+            upstream: Some(upstream.to_string()),
+            client_version: Some("reth/v1.1.2".to_string()),
+            latency_ms: Some(recorded_at * 10),
+            node_cache_version: "0.0.1".to_string(),
+            key_schema_version: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_each_session_once() {
+        let path = temp_path("sessions");
+        let recorder = PickleRecorder::new(path.clone()).unwrap();
+        recorder.record_with_provenance("a", &1, &provenance(1, "http://localhost:8545")).await.unwrap();
+        recorder.record_with_provenance("b", &2, &provenance(2, "http://localhost:8545")).await.unwrap();
+        recorder.record_with_provenance("c", &3, &provenance(3, "http://localhost:8546")).await.unwrap();
+        assert_eq!(recorder.provenance("b").await.unwrap(), Some(provenance(2, "http://localhost:8545")));
+        assert_eq!(recorder.provenance("c").await.unwrap(), Some(provenance(3, "http://localhost:8546")));
+        let sessions = recorder.db.read().await.get_all().into_iter().filter(|key| key.starts_with(SESSION_KEY_PREFIX)).count();
+        assert_eq!(sessions, 2);
+        assert_eq!(recorder.keys().await.unwrap().len(), 3);
+        recorder.save().await.unwrap();
+
+        // a loaded file continues with the stored sessions
+        let recorder = PickleRecorder::new(path.clone()).unwrap();
+        recorder.record_with_provenance("d", &4, &provenance(4, "http://localhost:8545")).await.unwrap();
+        assert_eq!(recorder.provenance("a").await.unwrap(), Some(provenance(1, "http://localhost:8545")));
+        assert_eq!(recorder.sessions.lock().unwrap().len(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recording_without_provenance_drops_the_previous_one() {
+        let recorder = PickleRecorder::new(temp_path("overwrite")).unwrap();
+        recorder.record_with_provenance("a", &1, &provenance(1, "http://localhost:8545")).await.unwrap();
+        recorder.record("a", &2).await.unwrap();
+        assert_eq!(recorder.get::<u64>("a").await.unwrap(), Some(2));
+        assert_eq!(recorder.provenance("a").await.unwrap(), None);
+    }
 }
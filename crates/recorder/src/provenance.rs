@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// When, from where and by which node cache version an entry was recorded
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Unix timestamp in seconds
+    pub recorded_at: u64,
+    /// Upstream node URL with credentials and API keys redacted
+    pub upstream: Option<String>,
+    /// Client version reported by the upstream node
+    pub client_version: Option<String>,
+    /// Duration of the upstream request in milliseconds
+    pub latency_ms: Option<u64>,
+    pub node_cache_version: String,
+    /// Version of the key format the entry was recorded with
+    pub key_schema_version: u32,
+}
@@ -1,9 +1,21 @@
+use crate::Provenance;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 #[async_trait::async_trait]
 pub trait Recorder {
     async fn record<T: Serialize + Send + Sync>(&self, key: &str, value: &T) -> eyre::Result<()>;
+    async fn record_with_provenance<T: Serialize + Send + Sync>(&self, key: &str, value: &T, provenance: &Provenance) -> eyre::Result<()>;
+    async fn provenance(&self, key: &str) -> eyre::Result<Option<Provenance>>;
     async fn get<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>>;
+    /// Read the entry without counting it as a use, for scans and internal checks
+    async fn peek<T: DeserializeOwned>(&self, key: &str) -> eyre::Result<Option<T>>;
+    async fn keys(&self) -> eyre::Result<Vec<String>>;
+    async fn remove(&self, key: &str) -> eyre::Result<()>;
+    /// Bytes the key, value and provenance of the entry take up in the database
+    async fn stored_size(&self, key: &str) -> eyre::Result<u64>;
     async fn save(&self) -> eyre::Result<()>;
+    /// Version of the key schema stored in the header of the database, `None` for unversioned databases
+    async fn schema_version(&self) -> eyre::Result<Option<u32>>;
+    async fn set_schema_version(&self, version: u32) -> eyre::Result<()>;
 }
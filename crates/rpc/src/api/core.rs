@@ -1,25 +1,50 @@
-use crate::helper::base::{build_inner, ApiInner};
-use crate::helper::error::{alloy_error, eyre_error};
+use crate::config::{NodeCacheConfig, SubmissionPolicy};
+use crate::helper::base::{build_inner, record_derived, ApiInner};
+use crate::helper::block::{block_key, is_fixed_block, transaction_key, BlockKey};
+use crate::helper::call::{simulate_payload_key, state_context_block, state_override_key};
+use crate::helper::code::code_at_key;
+use crate::helper::derive::{account_key, proof_key};
+use crate::helper::error::{alloy_error, eyre_error, is_method_not_found, not_recorded_error, submission_error};
+use crate::helper::state::{storage_at_key, StateField};
+use alloy_consensus::TxEnvelope;
 use alloy_dyn_abi::TypedData;
 use alloy_primitives::utils::parse_units;
-use alloy_primitives::{Address, Bytes, B256, B64, U256, U64};
-use alloy_provider::network::Ethereum;
+use alloy_primitives::{keccak256, Address, Bytes, B256, B64, U256, U64};
+use alloy_provider::network::eip2718::Decodable2718;
+use alloy_provider::network::{Ethereum, TransactionResponse};
 use alloy_provider::Provider;
 use alloy_rpc_types_eth::simulate::{SimulatePayload, SimulatedBlock};
 use alloy_rpc_types_eth::state::StateOverride;
 use alloy_rpc_types_eth::{
     AccessListResult, Account, BlockId, BlockNumberOrTag, BlockOverrides, Bundle, EIP1186AccountProofResponse, EthCallResponse, FeeHistory,
-    Header, Index, StateContext, SyncStatus, TransactionRequest, Work,
+    Header, Index, StateContext, SyncStatus, Transaction, TransactionRequest, Work,
 };
 use alloy_serde::JsonStorageKey;
-use alloy_transport::Transport;
+use alloy_transport::{Transport, TransportResult};
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::tokio;
 use jsonrpsee::tokio::runtime::Handle;
 use node_cache_recorder::Recorder;
 use reth_rpc::eth::EthereumEthApiTypes;
 use reth_rpc_eth_api::{EthApiServer, EthApiTypes, RpcBlock, RpcReceipt, RpcTransaction};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::warn;
+
+pub const TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX: &str = "transaction_by_sender_and_nonce";
+pub const GET_BALANCE_KEY_PREFIX: &str = "get_balance";
+pub const TRANSACTION_COUNT_KEY_PREFIX: &str = "transaction_count";
+pub const SIMULATE_V1_KEY_PREFIX: &str = "simulate_v1";
+pub const CALL_MANY_KEY_PREFIX: &str = "call_many";
+pub const FEE_HISTORY_KEY_PREFIX: &str = "fee_history";
+
+/// Time-varying methods recorded as sequences
+pub const BLOCK_NUMBER_SEQUENCE: &str = "block_number";
+pub const GAS_PRICE_SEQUENCE: &str = "gas_price";
+pub const MAX_PRIORITY_FEE_PER_GAS_SEQUENCE: &str = "max_priority_fee_per_gas";
+pub const BLOB_BASE_FEE_SEQUENCE: &str = "blob_base_fee";
 
 #[derive(Debug, Clone)]
 pub struct NodeCacheEthApi<T, P, R>
@@ -29,6 +54,9 @@ where
     R: Recorder,
 {
     inner: ApiInner<T, P, R>,
+    spot_prices: Arc<Mutex<HashMap<&'static str, (Instant, U256)>>>,
+    /// Pending transactions of the mock submission policy, never written to the record file
+    mocked_transactions: Arc<Mutex<HashMap<B256, Transaction>>>,
 }
 
 impl<T, P, R> NodeCacheEthApi<T, P, R>
@@ -37,8 +65,62 @@ where
     P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
     R: Recorder,
 {
-    pub fn new(provider: Option<P>, recorder: Arc<R>) -> Self {
-        Self { inner: build_inner(provider, recorder) }
+    pub fn new(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> Self {
+        Self {
+            inner: build_inner(provider, recorder, config),
+            spot_prices: Arc::new(Mutex::new(HashMap::new())),
+            mocked_transactions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn mocked_transaction(&self, hash: B256) -> Option<Transaction> {
+        self.mocked_transactions.lock().unwrap().get(&hash).cloned()
+    }
+
+    fn cached_spot_price(&self, method: &'static str) -> Option<U256> {
+        let spot_prices = self.spot_prices.lock().unwrap();
+        // spot prices change every block
+        let block_time = self.inner.config.block_time();
+        spot_prices.get(method).filter(|(recorded_at, _)| recorded_at.elapsed() < block_time).map(|(_, price)| *price)
+    }
+
+    fn record_spot_price(&self, method: &'static str, price: U256) {
+        self.spot_prices.lock().unwrap().insert(method, (Instant::now(), price));
+    }
+
+    /// Spot price of the provider, reused for one block time and recorded as a sequence. Without a provider the recorded sequence is
+    /// replayed, and once it runs out the price is derived from the pinned header
+    async fn spot_price<F, Fut>(&self, sequence: &'static str, fetch: F, derive: impl FnOnce(Option<Header>) -> U256) -> RpcResult<U256>
+    where
+        F: FnOnce(P) -> Fut,
+        Fut: Future<Output = TransportResult<u128>>,
+    {
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let price = match self.cached_spot_price(sequence) {
+                Some(price) => price,
+                None => {
+                    let price = U256::from(fetch(provider.clone()).await.map_err(alloy_error)?);
+                    self.record_spot_price(sequence, price);
+                    price
+                }
+            };
+            self.inner.record_sequence(sequence, &price, started).await?;
+            return Ok(price);
+        }
+        if let Some(price) = self.inner.replay_sequence(sequence).await? {
+            return Ok(price);
+        }
+        Ok(derive(self.inner.pinned_header().await?))
+    }
+
+    /// Provider for transactions and signing requests, if the submission policy allows to forward them
+    fn forward_provider(&self, method: &str) -> RpcResult<&P> {
+        match self.inner.config.submission_policy {
+            SubmissionPolicy::Forward => self.inner.provider.as_ref().ok_or_else(|| submission_error(method, "no provider to forward to")),
+            SubmissionPolicy::Reject => Err(submission_error(method, "transaction submission is rejected by the submission policy")),
+            SubmissionPolicy::Mock => Err(submission_error(method, "node cache has no accounts to sign with")),
+        }
     }
 }
 
@@ -55,41 +137,62 @@ where
     R: Recorder + Sync + Send + 'static,
 {
     async fn protocol_version(&self) -> RpcResult<U64> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return provider.raw_request("eth_protocolVersion".into(), ()).await.map_err(alloy_error);
+        }
+        Ok(self.inner.config.node_status.protocol_version)
     }
 
     fn syncing(&self) -> RpcResult<SyncStatus> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return tokio::task::block_in_place(move || Handle::current().block_on(provider.raw_request("eth_syncing".into(), ())))
+                .map_err(alloy_error);
+        }
+        Ok(SyncStatus::None)
     }
 
     async fn author(&self) -> RpcResult<Address> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return provider.raw_request("eth_coinbase".into(), ()).await.map_err(alloy_error);
+        }
+        Ok(self.inner.config.node_status.author)
     }
 
     fn accounts(&self) -> RpcResult<Vec<Address>> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return tokio::task::block_in_place(move || Handle::current().block_on(provider.raw_request("eth_accounts".into(), ())))
+                .map_err(alloy_error);
+        }
+        Ok(self.inner.config.node_status.accounts.clone())
     }
 
     fn block_number(&self) -> RpcResult<U256> {
-        if let Some(provider) = self.inner.provider.as_ref() {
-            let block_number =
-                tokio::task::block_in_place(move || Handle::current().block_on(provider.get_block_number())).map_err(alloy_error)?;
-
-            return Ok(U256::from(block_number));
+        if let Some(fork_block) = self.inner.config.fork_block {
+            return Ok(U256::from(fork_block));
         }
-        panic!("Provider required")
+        tokio::task::block_in_place(move || {
+            Handle::current().block_on(async {
+                if let Some(provider) = self.inner.provider.as_ref() {
+                    let started = Instant::now();
+                    let block_number = U256::from(provider.get_block_number().await.map_err(alloy_error)?);
+                    self.inner.record_sequence(BLOCK_NUMBER_SEQUENCE, &block_number, started).await?;
+                    return Ok(block_number);
+                }
+                self.inner.replay_sequence(BLOCK_NUMBER_SEQUENCE).await?.ok_or_else(|| not_recorded_error("eth_blockNumber"))
+            })
+        })
     }
 
     async fn chain_id(&self) -> RpcResult<Option<U64>> {
-        Ok(Some(U64::from(1)))
+        Ok(Some(U64::from(self.inner.chain_id().await?)))
     }
 
     async fn block_by_hash(
         &self,
-        _hash: B256,
-        _full: bool,
+        hash: B256,
+        full: bool,
     ) -> RpcResult<Option<RpcBlock<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        todo!()
+        self.inner.get_block(BlockKey::Hash(hash), full).await
     }
 
     async fn block_by_number(
@@ -97,36 +200,27 @@ where
         number: BlockNumberOrTag,
         full: bool,
     ) -> RpcResult<Option<RpcBlock<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        let key = format!("{}{}{}", "block_by_number", number, full);
-        // cache
-        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
-            return Ok(Some(ret));
-        }
-        // real provider
-        if let Some(provider) = self.inner.provider.as_ref() {
-            let result = provider.get_block_by_number(number, full.into()).await.map_err(alloy_error)?;
-            if let Some(ref block) = result {
-                self.inner.recorder.record(&key, block).await.map_err(eyre_error)?;
-            }
-            return Ok(result);
-        }
-        Ok(None)
+        self.inner.get_block(BlockKey::Number(number), full).await
     }
 
-    async fn block_transaction_count_by_hash(&self, _hash: B256) -> RpcResult<Option<U256>> {
-        todo!()
+    async fn block_transaction_count_by_hash(&self, hash: B256) -> RpcResult<Option<U256>> {
+        let block = self.inner.get_block(BlockKey::Hash(hash), false).await?;
+        Ok(block.map(|block| U256::from(block.transactions.len())))
     }
 
-    async fn block_transaction_count_by_number(&self, _number: BlockNumberOrTag) -> RpcResult<Option<U256>> {
-        todo!()
+    async fn block_transaction_count_by_number(&self, number: BlockNumberOrTag) -> RpcResult<Option<U256>> {
+        let block = self.inner.get_block(BlockKey::Number(number), false).await?;
+        Ok(block.map(|block| U256::from(block.transactions.len())))
     }
 
-    async fn block_uncles_count_by_hash(&self, _hash: B256) -> RpcResult<Option<U256>> {
-        todo!()
+    async fn block_uncles_count_by_hash(&self, hash: B256) -> RpcResult<Option<U256>> {
+        let block = self.inner.get_block(BlockKey::Hash(hash), false).await?;
+        Ok(block.map(|block| U256::from(block.uncles.len())))
     }
 
-    async fn block_uncles_count_by_number(&self, _number: BlockNumberOrTag) -> RpcResult<Option<U256>> {
-        todo!()
+    async fn block_uncles_count_by_number(&self, number: BlockNumberOrTag) -> RpcResult<Option<U256>> {
+        let block = self.inner.get_block(BlockKey::Number(number), false).await?;
+        Ok(block.map(|block| U256::from(block.uncles.len())))
     }
 
     async fn block_receipts(
@@ -138,18 +232,18 @@ where
 
     async fn uncle_by_block_hash_and_index(
         &self,
-        _hash: B256,
-        _index: Index,
+        hash: B256,
+        index: Index,
     ) -> RpcResult<Option<RpcBlock<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        todo!()
+        self.inner.get_uncle(BlockKey::Hash(hash), index).await
     }
 
     async fn uncle_by_block_number_and_index(
         &self,
-        _number: BlockNumberOrTag,
-        _index: Index,
+        number: BlockNumberOrTag,
+        index: Index,
     ) -> RpcResult<Option<RpcBlock<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        todo!()
+        self.inner.get_uncle(BlockKey::Number(number), index).await
     }
 
     async fn raw_transaction_by_hash(&self, _hash: B256) -> RpcResult<Option<Bytes>> {
@@ -160,50 +254,112 @@ where
         &self,
         hash: B256,
     ) -> RpcResult<Option<RpcTransaction<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        let key = format!("{}{}", "transaction_by_hash", hash);
+        let key = transaction_key(hash);
         // cache
-        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+        if let Some(ret) = self.inner.recorder.get::<Option<Transaction>>(&key).await.map_err(eyre_error)? {
+            self.inner.check_fork_transaction(ret.as_ref())?;
             return Ok(ret);
         }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = provider.get_transaction_by_hash(hash).await.map_err(alloy_error)?;
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
-            return Ok(result);
+            self.inner.check_fork_transaction(result.as_ref())?;
+            // pending transactions and transactions of reorgable blocks are not recorded with finality
+            let is_final = match result.as_ref().and_then(|transaction| transaction.block_number) {
+                Some(number) => self.inner.is_final_number(number).await?,
+                None => self.inner.config.finality.is_none(),
+            };
+            let verified = match result {
+                Some(ref transaction) => self.inner.verify_transaction(transaction).await?,
+                None => !self.inner.config.verify,
+            };
+            if is_final && verified {
+                self.inner.record(&key, &result, started).await?;
+                if let Some(ref transaction) = result {
+                    self.inner.index_transaction_entry(transaction, &key).await?;
+                }
+            }
+            // a mocked transaction is only known to node cache
+            return Ok(result.or_else(|| self.mocked_transaction(hash)));
         }
-        Ok(None)
+        Ok(self.mocked_transaction(hash))
     }
 
-    async fn raw_transaction_by_block_hash_and_index(&self, _hash: B256, _index: Index) -> RpcResult<Option<Bytes>> {
-        todo!()
+    async fn raw_transaction_by_block_hash_and_index(&self, hash: B256, index: Index) -> RpcResult<Option<Bytes>> {
+        self.inner.get_raw_block_transaction(BlockKey::Hash(hash), index).await
     }
 
     async fn transaction_by_block_hash_and_index(
         &self,
-        _hash: B256,
-        _index: Index,
+        hash: B256,
+        index: Index,
     ) -> RpcResult<Option<RpcTransaction<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        todo!()
+        self.inner.get_block_transaction(BlockKey::Hash(hash), index).await
     }
 
-    async fn raw_transaction_by_block_number_and_index(&self, _number: BlockNumberOrTag, _index: Index) -> RpcResult<Option<Bytes>> {
-        todo!()
+    async fn raw_transaction_by_block_number_and_index(&self, number: BlockNumberOrTag, index: Index) -> RpcResult<Option<Bytes>> {
+        self.inner.get_raw_block_transaction(BlockKey::Number(number), index).await
     }
 
     async fn transaction_by_block_number_and_index(
         &self,
-        _number: BlockNumberOrTag,
-        _index: Index,
+        number: BlockNumberOrTag,
+        index: Index,
     ) -> RpcResult<Option<RpcTransaction<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        todo!()
+        self.inner.get_block_transaction(BlockKey::Number(number), index).await
     }
 
     async fn transaction_by_sender_and_nonce(
         &self,
-        _address: Address,
-        _nonce: U64,
+        address: Address,
+        nonce: U64,
     ) -> RpcResult<Option<RpcTransaction<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
-        todo!()
+        let key = format!("{}{}{}", TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX, address, nonce);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(Some(ret));
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Result<Option<Transaction>, _> =
+                provider.raw_request("eth_getTransactionBySenderAndNonce".into(), (address, nonce)).await;
+            match result {
+                Ok(result) => {
+                    // a pending transaction might still be replaced, a transaction of a reorgable block as well
+                    if let Some(ref transaction) = result {
+                        let is_final = match transaction.block_number {
+                            Some(number) => self.inner.is_final_number(number).await?,
+                            None => false,
+                        };
+                        if is_final && self.inner.verify_transaction(transaction).await? {
+                            let provenance = self.inner.provenance(started);
+                            let hash_key = transaction_key(transaction.tx_hash());
+                            self.inner.record_with(&key, transaction, &provenance).await?;
+                            self.inner.record_with(&hash_key, transaction, &provenance).await?;
+                            self.inner.index_transaction_entry(transaction, &key).await?;
+                            self.inner.index_transaction_entry(transaction, &hash_key).await?;
+                        }
+                    }
+                    return Ok(result);
+                }
+                // only a node without the method is answered from cached blocks
+                Err(err) if self.inner.config.sender_nonce_fallback && is_method_not_found(&err) => {
+                    warn!("Provider does not support eth_getTransactionBySenderAndNonce, searching cached blocks")
+                }
+                Err(err) => return Err(alloy_error(err)),
+            }
+        }
+        if self.inner.config.sender_nonce_fallback {
+            let result = self.inner.cached_sender_transaction(address, nonce.to()).await?;
+            // derived from a cached block, the entry keeps the provenance of the block
+            if let Some((transaction, Some(block_hash))) = result.as_ref().map(|transaction| (transaction, transaction.block_hash)) {
+                record_derived(self.inner.recorder.as_ref(), &key, transaction, &block_key(block_hash)).await.map_err(eyre_error)?;
+            }
+            return Ok(result);
+        }
+        Ok(None)
     }
 
     async fn transaction_receipt(&self, _hash: B256) -> RpcResult<Option<RpcReceipt<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> {
@@ -211,31 +367,55 @@ where
     }
 
     async fn balance(&self, address: Address, block_number: Option<BlockId>) -> RpcResult<U256> {
-        let key = format!("{}{}{:?}", "get_balance", address, block_number);
+        let block_number = self.inner.pin_block_id(block_number).await?;
+        let key = format!("{}{}{:?}", GET_BALANCE_KEY_PREFIX, address, block_number);
         // cache
         if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
             return Ok(ret);
         }
+        // derived from a richer cached response of the same block
+        if let Some(ret) = self.inner.derive_balance(address, block_number).await? {
+            return Ok(ret);
+        }
+        // derived from a recorded block without changes in between
+        if let Some(ret) = self.inner.derive_state(StateField::Balance, address, block_number).await? {
+            return Ok(ret);
+        }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = match block_number {
                 Some(block_id) => provider.get_balance(address).block_id(block_id).await.map_err(alloy_error)?,
                 None => provider.get_balance(address).await.map_err(alloy_error)?,
             };
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
+            if !self.inner.verify_account("eth_getBalance", address, None, block_number, |proof| proof.balance == result).await? {
+                return Ok(result);
+            }
+            self.inner.record_for_block(block_number, &key, &result, started).await?;
+            self.inner.record_state(StateField::Balance, address, block_number, &result, started).await?;
             return Ok(result);
         }
         Ok(U256::ZERO)
     }
 
     async fn storage_at(&self, address: Address, index: JsonStorageKey, block_number: Option<BlockId>) -> RpcResult<B256> {
-        let key = format!("{}{}{}{:?}", "storage_at", address, index, block_number);
+        let block_number = self.inner.pin_block_id(block_number).await?;
+        let key = storage_at_key(address, index, block_number);
         // cache
         if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
             return Ok(ret);
         }
+        // derived from a richer cached response of the same block
+        if let Some(ret) = self.inner.derive_storage(address, index.as_b256(), block_number).await? {
+            return Ok(ret);
+        }
+        // derived from a recorded block without changes in between
+        if let Some(ret) = self.inner.derive_state(StateField::Storage(index.as_b256()), address, block_number).await? {
+            return Ok(ret);
+        }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = match block_number {
                 Some(block_id) => provider
                     .get_storage_at(address, U256::from_be_slice(index.as_b256().as_slice()))
@@ -244,62 +424,130 @@ where
                     .map_err(alloy_error)?,
                 None => provider.get_storage_at(address, U256::from_be_slice(index.as_b256().as_slice())).await.map_err(alloy_error)?,
             };
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
-            return Ok(B256::from(result));
+            let result = B256::from(result);
+            let slot = index.as_b256();
+            let verified = self
+                .inner
+                .verify_account("eth_getStorageAt", address, Some(slot), block_number, |proof| {
+                    proof
+                        .storage_proof
+                        .iter()
+                        .any(|storage_proof| storage_proof.key.as_b256() == slot && B256::from(storage_proof.value) == result)
+                })
+                .await?;
+            if !verified {
+                return Ok(result);
+            }
+            self.inner.record_for_block(block_number, &key, &result, started).await?;
+            self.inner.record_state(StateField::Storage(index.as_b256()), address, block_number, &result, started).await?;
+            return Ok(result);
         }
         Ok(B256::ZERO)
     }
 
     async fn transaction_count(&self, address: Address, block_number: Option<BlockId>) -> RpcResult<U256> {
-        let key = format!("{}{}{:?}", "transaction_count", address, block_number);
+        let block_number = self.inner.pin_block_id(block_number).await?;
+        let key = format!("{}{}{:?}", TRANSACTION_COUNT_KEY_PREFIX, address, block_number);
         // cache
         if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
             return Ok(ret);
         }
+        // derived from a richer cached response of the same block
+        if let Some(ret) = self.inner.derive_nonce(address, block_number).await? {
+            return Ok(ret);
+        }
+        // derived from a recorded block without changes in between
+        if let Some(ret) = self.inner.derive_state(StateField::Nonce, address, block_number).await? {
+            return Ok(ret);
+        }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = match block_number {
                 Some(block_id) => provider.get_transaction_count(address).block_id(block_id).await.map_err(alloy_error)?,
                 None => provider.get_transaction_count(address).await.map_err(alloy_error)?,
             };
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
+            if !self.inner.verify_account("eth_getTransactionCount", address, None, block_number, |proof| proof.nonce == result).await? {
+                return Ok(U256::from(result));
+            }
+            self.inner.record_for_block(block_number, &key, &result, started).await?;
+            self.inner.record_state(StateField::Nonce, address, block_number, &U256::from(result), started).await?;
             return Ok(U256::from(result));
         }
         Ok(U256::ZERO)
     }
 
     async fn get_code(&self, address: Address, block_number: Option<BlockId>) -> RpcResult<Bytes> {
-        let key = format!("{}{}{:?}", "get_code", address, block_number);
+        let block_number = self.inner.pin_block_id(block_number).await?;
+        let key = code_at_key(address, block_number);
         // cache
-        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+        if let Some(ret) = self.inner.cached_code(&key).await? {
+            return Ok(ret);
+        }
+        // derived from a richer cached response of the same block
+        if let Some(ret) = self.inner.derive_code(address, block_number).await? {
             return Ok(ret);
         }
+        // derived from a recorded block without changes in between
+        if let Some(code_hash) = self.inner.derive_state(StateField::Code, address, block_number).await? {
+            if let Some(ret) = self.inner.code_by_hash(code_hash).await? {
+                return Ok(ret);
+            }
+        }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = match block_number {
                 Some(block_id) => provider.get_code_at(address).block_id(block_id).await.map_err(alloy_error)?,
                 None => provider.get_code_at(address).await.map_err(alloy_error)?,
             };
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
+            let code_hash = keccak256(&result);
+            if !self.inner.verify_account("eth_getCode", address, None, block_number, |proof| proof.code_hash == code_hash).await? {
+                return Ok(result);
+            }
+            if self.inner.is_final(block_number).await? {
+                self.inner.record_code(&key, code_hash, &result, started).await?;
+                self.inner.index_block_entry(block_number, &key).await?;
+            }
+            self.inner.record_state(StateField::Code, address, block_number, &code_hash, started).await?;
             return Ok(result);
         }
         Ok(Bytes::new())
     }
 
-    async fn header_by_number(&self, _hash: BlockNumberOrTag) -> RpcResult<Option<Header>> {
-        todo!()
+    async fn header_by_number(&self, number: BlockNumberOrTag) -> RpcResult<Option<Header>> {
+        let block = self.inner.get_block(BlockKey::Number(number), false).await?;
+        Ok(block.map(|block| block.header))
     }
 
-    async fn header_by_hash(&self, _hash: B256) -> RpcResult<Option<Header>> {
-        todo!()
+    async fn header_by_hash(&self, hash: B256) -> RpcResult<Option<Header>> {
+        let block = self.inner.get_block(BlockKey::Hash(hash), false).await?;
+        Ok(block.map(|block| block.header))
     }
 
     async fn simulate_v1(
         &self,
-        _opts: SimulatePayload,
-        _block_number: Option<BlockId>,
+        opts: SimulatePayload,
+        block_number: Option<BlockId>,
     ) -> RpcResult<Vec<SimulatedBlock<RpcBlock<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>>> {
-        todo!()
+        let block_number = self.inner.pin_block_id(block_number).await?;
+        let key = format!("{}{}{:?}", SIMULATE_V1_KEY_PREFIX, simulate_payload_key(&opts), block_number);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Vec<SimulatedBlock<RpcBlock<<EthereumEthApiTypes as EthApiTypes>::NetworkTypes>>> =
+                provider.raw_request("eth_simulateV1".into(), (opts, block_number)).await.map_err(alloy_error)?;
+            // simulated blocks on top of a moving tag change with the chain
+            if block_number.is_some_and(is_fixed_block) {
+                self.inner.record_for_block(block_number, &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("eth_simulateV1"))
     }
 
     async fn call(
@@ -314,11 +562,29 @@ where
 
     async fn call_many(
         &self,
-        _bundle: Bundle,
-        _state_context: Option<StateContext>,
-        _state_override: Option<StateOverride>,
+        bundle: Bundle,
+        state_context: Option<StateContext>,
+        state_override: Option<StateOverride>,
     ) -> RpcResult<Vec<EthCallResponse>> {
-        todo!()
+        let state_context = self.inner.pin_state_context(state_context).await?;
+        let key = format!("{}{:?}{:?}{}", CALL_MANY_KEY_PREFIX, bundle, state_context, state_override_key(state_override.as_ref()));
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let block = state_context_block(state_context.as_ref());
+            let result: Vec<EthCallResponse> =
+                provider.raw_request("eth_callMany".into(), (bundle, state_context, state_override)).await.map_err(alloy_error)?;
+            // calls on top of a moving tag change with the chain
+            if block.is_some_and(is_fixed_block) {
+                self.inner.record_for_block(block, &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(vec![])
     }
 
     async fn create_access_list(&self, _request: TransactionRequest, _block_number: Option<BlockId>) -> RpcResult<AccessListResult> {
@@ -335,55 +601,117 @@ where
     }
 
     async fn gas_price(&self) -> RpcResult<U256> {
-        if let Some(provider) = self.inner.provider.as_ref() {
-            let gas_price = provider.get_gas_price().await.map_err(alloy_error)?;
-            return Ok(U256::from(gas_price));
-        }
-        Ok(parse_units("1", "gwei").unwrap().get_absolute())
+        self.spot_price(
+            GAS_PRICE_SEQUENCE,
+            |provider| async move { provider.get_gas_price().await },
+            |header| {
+                // base fee of the pinned block plus a priority fee of one gwei
+                let priority_fee = parse_units("1", "gwei").unwrap().get_absolute();
+                match header.and_then(|header| header.base_fee_per_gas) {
+                    Some(base_fee) => U256::from(base_fee) + priority_fee,
+                    None => priority_fee,
+                }
+            },
+        )
+        .await
     }
 
     async fn get_account(&self, address: Address, block: BlockId) -> RpcResult<Option<Account>> {
-        let key = format!("{}{}{}", "get_account", address, block);
-        // cache
-        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+        let block = self.inner.pin_block_id(Some(block)).await?.unwrap_or(block);
+        let key = account_key(address, block);
+        // cache, or derived from a cached proof of the same block
+        if let Some(ret) = self.inner.derive_account(address, Some(block)).await? {
             return Ok(Some(ret));
         }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = provider.get_account(address).block_id(block).await.map_err(alloy_error)?;
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
+            let verified = self
+                .inner
+                .verify_account("eth_getAccount", address, None, Some(block), |proof| {
+                    proof.balance == result.balance && proof.nonce == result.nonce && proof.code_hash == result.code_hash
+                })
+                .await?;
+            if !verified {
+                return Ok(Some(result));
+            }
+            self.inner.record_for_block(Some(block), &key, &result, started).await?;
             return Ok(Some(result));
         }
         Ok(None)
     }
 
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
-        todo!()
+        self.spot_price(
+            MAX_PRIORITY_FEE_PER_GAS_SEQUENCE,
+            |provider| async move { provider.get_max_priority_fee_per_gas().await },
+            |_| parse_units("1", "gwei").unwrap().get_absolute(),
+        )
+        .await
     }
 
     async fn blob_base_fee(&self) -> RpcResult<U256> {
-        todo!()
+        self.spot_price(
+            BLOB_BASE_FEE_SEQUENCE,
+            |provider| async move { provider.get_blob_base_fee().await },
+            |header| {
+                // blob base fee of the block following the pinned block, or the minimum blob base fee
+                match header.and_then(|header| header.next_block_blob_fee()) {
+                    Some(blob_base_fee) => U256::from(blob_base_fee),
+                    None => U256::from(1),
+                }
+            },
+        )
+        .await
     }
 
     async fn fee_history(
         &self,
-        _block_count: U64,
-        _newest_block: BlockNumberOrTag,
-        _reward_percentiles: Option<Vec<f64>>,
+        block_count: U64,
+        newest_block: BlockNumberOrTag,
+        reward_percentiles: Option<Vec<f64>>,
     ) -> RpcResult<FeeHistory> {
-        todo!()
+        let newest_block = self.inner.pin_block_number(newest_block)?;
+        let key = format!("{}{}{}{:?}", FEE_HISTORY_KEY_PREFIX, block_count, newest_block, reward_percentiles);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result = provider
+                .get_fee_history(block_count.to(), newest_block, reward_percentiles.as_deref().unwrap_or_default())
+                .await
+                .map_err(alloy_error)?;
+            if is_fixed_block(newest_block.into()) {
+                self.inner.record_for_block(Some(newest_block.into()), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("eth_feeHistory"))
     }
 
     async fn is_mining(&self) -> RpcResult<bool> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return provider.raw_request("eth_mining".into(), ()).await.map_err(alloy_error);
+        }
+        Ok(false)
     }
 
     async fn hashrate(&self) -> RpcResult<U256> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return provider.raw_request("eth_hashrate".into(), ()).await.map_err(alloy_error);
+        }
+        Ok(U256::ZERO)
     }
 
     async fn get_work(&self) -> RpcResult<Work> {
-        todo!()
+        if let Some(provider) = self.inner.provider.as_ref() {
+            return provider.raw_request("eth_getWork".into(), ()).await.map_err(alloy_error);
+        }
+        Err(not_recorded_error("eth_getWork"))
     }
 
     async fn submit_hashrate(&self, _hashrate: U256, _id: B256) -> RpcResult<bool> {
@@ -394,32 +722,79 @@ where
         todo!()
     }
 
-    async fn send_transaction(&self, _request: TransactionRequest) -> RpcResult<B256> {
-        todo!()
+    async fn send_transaction(&self, request: TransactionRequest) -> RpcResult<B256> {
+        let provider = self.forward_provider("eth_sendTransaction")?;
+        provider.raw_request("eth_sendTransaction".into(), (request,)).await.map_err(alloy_error)
+    }
+
+    async fn send_raw_transaction(&self, bytes: Bytes) -> RpcResult<B256> {
+        if self.inner.config.submission_policy == SubmissionPolicy::Mock {
+            let envelope = TxEnvelope::decode_2718(&mut bytes.as_ref())
+                .map_err(|err| submission_error("eth_sendRawTransaction", &format!("invalid transaction: {}", err)))?;
+            let from = envelope
+                .recover_signer()
+                .map_err(|err| submission_error("eth_sendRawTransaction", &format!("invalid signature: {}", err)))?;
+            let hash = *envelope.tx_hash();
+            // visible as pending transaction for transaction_by_hash
+            let transaction = Transaction {
+                inner: envelope,
+                block_hash: None,
+                block_number: None,
+                transaction_index: None,
+                effective_gas_price: None,
+                from,
+            };
+            self.mocked_transactions.lock().unwrap().insert(hash, transaction);
+            return Ok(hash);
+        }
+        let provider = self.forward_provider("eth_sendRawTransaction")?;
+        provider.raw_request("eth_sendRawTransaction".into(), (bytes,)).await.map_err(alloy_error)
     }
 
-    async fn send_raw_transaction(&self, _bytes: Bytes) -> RpcResult<B256> {
-        todo!()
+    async fn sign(&self, address: Address, message: Bytes) -> RpcResult<Bytes> {
+        let provider = self.forward_provider("eth_sign")?;
+        provider.raw_request("eth_sign".into(), (address, message)).await.map_err(alloy_error)
     }
 
-    async fn sign(&self, _address: Address, _message: Bytes) -> RpcResult<Bytes> {
-        todo!()
+    async fn sign_transaction(&self, transaction: TransactionRequest) -> RpcResult<Bytes> {
+        let provider = self.forward_provider("eth_signTransaction")?;
+        provider.raw_request("eth_signTransaction".into(), (transaction,)).await.map_err(alloy_error)
     }
 
-    async fn sign_transaction(&self, _transaction: TransactionRequest) -> RpcResult<Bytes> {
-        todo!()
-    }
-
-    async fn sign_typed_data(&self, _address: Address, _data: TypedData) -> RpcResult<Bytes> {
-        todo!()
+    async fn sign_typed_data(&self, address: Address, data: TypedData) -> RpcResult<Bytes> {
+        let provider = self.forward_provider("eth_signTypedData")?;
+        provider.raw_request("eth_signTypedData".into(), (address, data)).await.map_err(alloy_error)
     }
 
     async fn get_proof(
         &self,
-        _address: Address,
-        _keys: Vec<JsonStorageKey>,
-        _block_number: Option<BlockId>,
+        address: Address,
+        keys: Vec<JsonStorageKey>,
+        block_number: Option<BlockId>,
     ) -> RpcResult<EIP1186AccountProofResponse> {
-        todo!()
+        let block_number = self.inner.pin_block_id(block_number).await?;
+        let key = proof_key(address, &keys, block_number);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: EIP1186AccountProofResponse = provider
+                .raw_request("eth_getProof".into(), (address, &keys, block_number.unwrap_or(BlockId::latest())))
+                .await
+                .map_err(alloy_error)?;
+            if !self.inner.verify_proof(&result, block_number).await? {
+                return Ok(result);
+            }
+            if self.inner.is_final(block_number).await? {
+                self.inner.record(&key, &result, started).await?;
+                self.inner.index_block_entry(block_number, &key).await?;
+                self.inner.index_proof(address, &keys, block_number, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("eth_getProof"))
     }
 }
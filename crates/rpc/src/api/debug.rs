@@ -1,5 +1,11 @@
+use crate::api::debug_ext::{DebugExtApiServer, StorageRangeResult};
+use crate::config::NodeCacheConfig;
 use crate::helper::base::{build_inner, ApiInner};
-use crate::helper::error::{alloy_error, eyre_error};
+use crate::helper::block::{is_fixed_block, BlockKey};
+use crate::helper::call::state_context_block;
+use crate::helper::error::{alloy_error, debug_ext_error, eyre_error, not_recorded_error};
+use crate::helper::state::storage_at_key;
+use crate::helper::trace::{trace_transaction_key, tracing_call_options_key, tracing_options_key};
 use alloy_primitives::{Address, Bytes, B256};
 use alloy_provider::ext::DebugApi;
 use alloy_provider::network::Ethereum;
@@ -12,9 +18,21 @@ use alloy_rpc_types_trace::geth::{
 use alloy_transport::Transport;
 use async_trait::async_trait;
 use jsonrpsee::core::RpcResult;
-use node_cache_recorder::Recorder;
+use node_cache_recorder::{Provenance, Recorder};
 use reth_rpc_api::DebugApiServer;
 use std::sync::Arc;
+use std::time::Instant;
+
+pub const DEBUG_STORAGE_RANGE_AT_KEY_PREFIX: &str = "debug_storage_range_at";
+pub const DEBUG_ACCOUNT_RANGE_KEY_PREFIX: &str = "debug_account_range";
+pub const DEBUG_RAW_HEADER_KEY_PREFIX: &str = "debug_raw_header";
+pub const DEBUG_RAW_BLOCK_KEY_PREFIX: &str = "debug_raw_block";
+pub const DEBUG_RAW_TRANSACTIONS_KEY_PREFIX: &str = "debug_raw_transactions";
+pub const DEBUG_RAW_RECEIPTS_KEY_PREFIX: &str = "debug_raw_receipts";
+pub const DEBUG_TRACE_BLOCK_BY_NUMBER_KEY_PREFIX: &str = "debug_trace_block_by_number";
+pub const DEBUG_TRACE_CALL_KEY_PREFIX: &str = "debug_trace_call";
+pub const DEBUG_TRACE_CALL_MANY_KEY_PREFIX: &str = "debug_trace_call_many";
+pub const DEBUG_EXECUTION_WITNESS_KEY_PREFIX: &str = "debug_execution_witness";
 
 pub struct NodeCacheDebugApi<T, P, R>
 where
@@ -31,8 +49,119 @@ where
     P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
     R: Recorder,
 {
-    pub fn new(provider: Option<P>, recorder: Arc<R>) -> Self {
-        Self { inner: build_inner(provider, recorder) }
+    pub fn new(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> Self {
+        Self { inner: build_inner(provider, recorder, config) }
+    }
+}
+
+impl<T, P, R> NodeCacheDebugApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// A storage range at the first transaction is the state of the parent block and after the last
+    /// transaction the state of the block itself. Only these can be stored as `eth_getStorageAt` entries.
+    async fn seed_storage(
+        &self,
+        block_hash: B256,
+        tx_idx: usize,
+        address: Address,
+        result: &StorageRangeResult,
+        provenance: &Provenance,
+    ) -> RpcResult<()> {
+        let Some(block) = self.inner.lookup_block(BlockKey::Hash(block_hash), false).await? else {
+            return Ok(());
+        };
+        let block_ids = if tx_idx >= block.transactions.len() {
+            vec![BlockId::hash(block_hash), BlockId::number(block.header.number)]
+        } else if tx_idx == 0 && block.header.number > 0 {
+            vec![BlockId::hash(block.header.parent_hash), BlockId::number(block.header.number - 1)]
+        } else {
+            return Ok(());
+        };
+        for entry in result.storage.values() {
+            // without the preimage the slot is unknown
+            let Some(slot) = entry.key else {
+                continue;
+            };
+            for block_id in block_ids.iter() {
+                let key = storage_at_key(address, slot.into(), Some(*block_id));
+                self.inner.record_with(&key, &entry.value, provenance).await?;
+                self.inner.index_block_entry(Some(*block_id), &key).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T, P, R> DebugExtApiServer for NodeCacheDebugApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    async fn storage_range_at(
+        &self,
+        block_hash: B256,
+        tx_idx: usize,
+        contract_address: Address,
+        key_start: B256,
+        max_result: u64,
+    ) -> RpcResult<StorageRangeResult> {
+        let key = format!("{}{}{}{}{}{}", DEBUG_STORAGE_RANGE_AT_KEY_PREFIX, block_hash, tx_idx, contract_address, key_start, max_result);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: StorageRangeResult = provider
+                .raw_request("debug_storageRangeAt".into(), (block_hash, tx_idx, contract_address, key_start, max_result))
+                .await
+                .map_err(alloy_error)?;
+            if !self.inner.is_final(Some(BlockId::hash(block_hash))).await? {
+                return Ok(result);
+            }
+            let provenance = self.inner.provenance(started);
+            self.inner.record_with(&key, &result, &provenance).await?;
+            if self.inner.config.seed_storage {
+                self.seed_storage(block_hash, tx_idx, contract_address, &result, &provenance).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_storageRangeAt"))
+    }
+
+    async fn account_range(
+        &self,
+        block_number: BlockNumberOrTag,
+        start: Bytes,
+        max_results: u64,
+        nocode: bool,
+        nostorage: bool,
+        incompletes: bool,
+    ) -> RpcResult<serde_json::Value> {
+        let block_number = self.inner.pin_block_number(block_number)?;
+        let key =
+            format!("{}{}{}{}{}{}{}", DEBUG_ACCOUNT_RANGE_KEY_PREFIX, block_number, start, max_results, nocode, nostorage, incompletes);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: serde_json::Value = provider
+                .raw_request("debug_accountRange".into(), (block_number, start, max_results, nocode, nostorage, incompletes))
+                .await
+                .map_err(alloy_error)?;
+            self.inner.record_for_block(Some(block_number.into()), &key, &result, started).await?;
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_accountRange"))
     }
 }
 
@@ -43,24 +172,84 @@ where
     P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
     R: Recorder + Sync + Send + 'static,
 {
-    async fn raw_header(&self, _block_id: BlockId) -> RpcResult<Bytes> {
-        todo!()
+    async fn raw_header(&self, block_id: BlockId) -> RpcResult<Bytes> {
+        let block_id = self.inner.pin_block_id(Some(block_id)).await?.unwrap_or(block_id);
+        let key = format!("{}{}", DEBUG_RAW_HEADER_KEY_PREFIX, block_id);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Bytes = provider.raw_request("debug_getRawHeader".into(), (block_id,)).await.map_err(alloy_error)?;
+            if is_fixed_block(block_id) && self.inner.verify_raw_header(block_id, &result).await? {
+                self.inner.record_for_block(Some(block_id), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_getRawHeader"))
     }
 
-    async fn raw_block(&self, _block_id: BlockId) -> RpcResult<Bytes> {
-        todo!()
+    async fn raw_block(&self, block_id: BlockId) -> RpcResult<Bytes> {
+        let block_id = self.inner.pin_block_id(Some(block_id)).await?.unwrap_or(block_id);
+        let key = format!("{}{}", DEBUG_RAW_BLOCK_KEY_PREFIX, block_id);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Bytes = provider.raw_request("debug_getRawBlock".into(), (block_id,)).await.map_err(alloy_error)?;
+            if is_fixed_block(block_id) {
+                self.inner.record_for_block(Some(block_id), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_getRawBlock"))
     }
 
     async fn raw_transaction(&self, _hash: B256) -> RpcResult<Option<Bytes>> {
         todo!()
     }
 
-    async fn raw_transactions(&self, _block_id: BlockId) -> RpcResult<Vec<Bytes>> {
-        todo!()
+    async fn raw_transactions(&self, block_id: BlockId) -> RpcResult<Vec<Bytes>> {
+        let block_id = self.inner.pin_block_id(Some(block_id)).await?.unwrap_or(block_id);
+        let key = format!("{}{}", DEBUG_RAW_TRANSACTIONS_KEY_PREFIX, block_id);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Vec<Bytes> = provider.raw_request("debug_getRawTransactions".into(), (block_id,)).await.map_err(alloy_error)?;
+            if is_fixed_block(block_id) && self.inner.verify_raw_transactions(block_id, &result).await? {
+                self.inner.record_for_block(Some(block_id), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_getRawTransactions"))
     }
 
-    async fn raw_receipts(&self, _block_id: BlockId) -> RpcResult<Vec<Bytes>> {
-        todo!()
+    async fn raw_receipts(&self, block_id: BlockId) -> RpcResult<Vec<Bytes>> {
+        let block_id = self.inner.pin_block_id(Some(block_id)).await?.unwrap_or(block_id);
+        let key = format!("{}{}", DEBUG_RAW_RECEIPTS_KEY_PREFIX, block_id);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Vec<Bytes> = provider.raw_request("debug_getRawReceipts".into(), (block_id,)).await.map_err(alloy_error)?;
+            if is_fixed_block(block_id) && self.inner.verify_raw_receipts(block_id, &result).await? {
+                self.inner.record_for_block(Some(block_id), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_getRawReceipts"))
     }
 
     async fn bad_blocks(&self) -> RpcResult<Vec<Block>> {
@@ -88,29 +277,60 @@ where
         block: BlockNumberOrTag,
         opts: Option<GethDebugTracingOptions>,
     ) -> RpcResult<Vec<TraceResult>> {
-        let key = format!("{}{}{:?}", "debug_trace_block_by_number", block, opts);
+        let block = self.inner.pin_block_number(block)?;
+        let key = format!("{}{}{}", DEBUG_TRACE_BLOCK_BY_NUMBER_KEY_PREFIX, block, tracing_options_key(opts.as_ref()));
         // cache
         if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
             return Ok(ret);
         }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
-            let result = match opts {
+            let started = Instant::now();
+            let result = match opts.clone() {
                 Some(opts) => provider.debug_trace_block_by_number(block, opts).await.map_err(alloy_error)?,
                 None => {
                     let opts = GethDebugTracingOptions::default();
                     provider.debug_trace_block_by_number(block, opts).await.map_err(alloy_error)?
                 }
             };
-
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
+            if !self.inner.is_final(Some(block.into())).await? {
+                return Ok(result);
+            }
+
+            let provenance = self.inner.provenance(started);
+            self.inner.record_with(&key, &result, &provenance).await?;
+            self.inner.index_block_entry(Some(block.into()), &key).await?;
+            // every transaction trace of the block answers debug_traceTransaction with the same tracer
+            for trace in result.iter() {
+                if let TraceResult::Success { result: trace, tx_hash: Some(tx_hash) } = trace {
+                    let trace_key = trace_transaction_key(*tx_hash, opts.as_ref());
+                    self.inner.record_with(&trace_key, trace, &provenance).await?;
+                    self.inner.index_block_entry(Some(block.into()), &trace_key).await?;
+                }
+            }
+            if let BlockNumberOrTag::Number(number) = block {
+                self.inner.record_state_changes(number, opts.as_ref(), &result, &provenance).await?;
+            }
             return Ok(result);
         }
         Ok(vec![])
     }
 
-    async fn debug_trace_transaction(&self, _tx_hash: B256, _opts: Option<GethDebugTracingOptions>) -> RpcResult<GethTrace> {
-        todo!()
+    async fn debug_trace_transaction(&self, tx_hash: B256, opts: Option<GethDebugTracingOptions>) -> RpcResult<GethTrace> {
+        self.inner.check_fork_transaction_hash(tx_hash).await?;
+        let key = trace_transaction_key(tx_hash, opts.as_ref());
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result = provider.debug_trace_transaction(tx_hash, opts.unwrap_or_default()).await.map_err(alloy_error)?;
+            self.inner.record_for_transaction(tx_hash, &key, &result, started).await?;
+            return Ok(result);
+        }
+        Ok(GethTrace::Default(DefaultFrame::default()))
     }
 
     async fn debug_trace_call(
@@ -119,13 +339,15 @@ where
         block_id: Option<BlockId>,
         opts: Option<GethDebugTracingCallOptions>,
     ) -> RpcResult<GethTrace> {
-        let key = format!("{}{:?}{:?}{:?}", "debug_trace_call", request, block_id, opts);
+        let block_id = self.inner.pin_block_id(block_id).await?;
+        let key = format!("{}{:?}{:?}{}", DEBUG_TRACE_CALL_KEY_PREFIX, request, block_id, tracing_call_options_key(opts.as_ref()));
         // cache
         if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
             return Ok(ret);
         }
         // real provider
         if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
             let result = match opts {
                 Some(opts) => provider.debug_trace_call(request, block_id.unwrap(), opts).await.map_err(alloy_error)?,
                 None => {
@@ -133,7 +355,7 @@ where
                     provider.debug_trace_call(request, block_id.unwrap(), opts).await.map_err(alloy_error)?
                 }
             };
-            self.inner.recorder.record(&key, &result).await.map_err(eyre_error)?;
+            self.inner.record_for_block(block_id, &key, &result, started).await?;
             return Ok(result);
         }
         Ok(GethTrace::Default(DefaultFrame::default()))
@@ -141,15 +363,49 @@ where
 
     async fn debug_trace_call_many(
         &self,
-        _bundles: Vec<Bundle>,
-        _state_context: Option<StateContext>,
-        _opts: Option<GethDebugTracingCallOptions>,
+        bundles: Vec<Bundle>,
+        state_context: Option<StateContext>,
+        opts: Option<GethDebugTracingCallOptions>,
     ) -> RpcResult<Vec<Vec<GethTrace>>> {
-        todo!()
+        let state_context = self.inner.pin_state_context(state_context).await?;
+        let key =
+            format!("{}{:?}{:?}{}", DEBUG_TRACE_CALL_MANY_KEY_PREFIX, bundles, state_context, tracing_call_options_key(opts.as_ref()));
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let block = state_context_block(state_context.as_ref());
+            let result: Vec<Vec<GethTrace>> =
+                provider.raw_request("debug_traceCallMany".into(), (bundles, state_context, opts)).await.map_err(alloy_error)?;
+            // calls on top of a moving tag change with the chain
+            if block.is_some_and(is_fixed_block) {
+                self.inner.record_for_block(block, &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(vec![])
     }
 
-    async fn debug_execution_witness(&self, _block: BlockNumberOrTag) -> RpcResult<ExecutionWitness> {
-        todo!()
+    async fn debug_execution_witness(&self, block: BlockNumberOrTag) -> RpcResult<ExecutionWitness> {
+        let block = self.inner.pin_block_number(block)?;
+        let key = format!("{}{}", DEBUG_EXECUTION_WITNESS_KEY_PREFIX, block);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: ExecutionWitness = provider.raw_request("debug_executionWitness".into(), (block,)).await.map_err(alloy_error)?;
+            if is_fixed_block(block.into()) {
+                self.inner.record_for_block(Some(block.into()), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("debug_executionWitness"))
     }
 
     async fn debug_backtrace_at(&self, _location: &str) -> RpcResult<()> {
@@ -165,7 +421,7 @@ where
         _nostorage: bool,
         _incompletes: bool,
     ) -> RpcResult<()> {
-        todo!()
+        Err(debug_ext_error("debug_accountRange"))
     }
 
     async fn debug_block_profile(&self, _file: String, _seconds: u64) -> RpcResult<()> {
@@ -316,7 +572,7 @@ where
         _key_start: B256,
         _max_result: u64,
     ) -> RpcResult<()> {
-        todo!()
+        Err(debug_ext_error("debug_storageRangeAt"))
     }
 
     async fn debug_trace_bad_block(&self, _block_hash: B256, _opts: Option<GethDebugTracingCallOptions>) -> RpcResult<()> {
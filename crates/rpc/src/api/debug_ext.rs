@@ -0,0 +1,46 @@
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_rpc_types_eth::BlockNumberOrTag;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::proc_macros::rpc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Debug methods with a proper return type, the reth `DebugApi` declares them without a result
+#[rpc(server, namespace = "debug")]
+pub trait DebugExtApi {
+    #[method(name = "storageRangeAt")]
+    async fn storage_range_at(
+        &self,
+        block_hash: B256,
+        tx_idx: usize,
+        contract_address: Address,
+        key_start: B256,
+        max_result: u64,
+    ) -> RpcResult<StorageRangeResult>;
+
+    #[method(name = "accountRange")]
+    async fn account_range(
+        &self,
+        block_number: BlockNumberOrTag,
+        start: Bytes,
+        max_results: u64,
+        nocode: bool,
+        nostorage: bool,
+        incompletes: bool,
+    ) -> RpcResult<serde_json::Value>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageRangeResult {
+    /// Entries by hashed storage key
+    pub storage: BTreeMap<B256, StorageEntry>,
+    pub next_key: Option<B256>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageEntry {
+    /// Preimage of the hashed storage key, if known by the node
+    pub key: Option<B256>,
+    pub value: B256,
+}
@@ -1,2 +1,7 @@
 pub mod core;
 pub mod debug;
+pub mod debug_ext;
+pub mod net;
+pub mod otterscan;
+pub mod trace;
+pub mod web3;
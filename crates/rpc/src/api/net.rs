@@ -0,0 +1,52 @@
+use crate::config::NodeCacheConfig;
+use crate::helper::base::{build_inner, ApiInner};
+use alloy_primitives::U64;
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::tokio;
+use jsonrpsee::tokio::runtime::Handle;
+use node_cache_recorder::Recorder;
+use reth_rpc_api::NetApiServer;
+use std::sync::Arc;
+
+pub struct NodeCacheNetApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    inner: ApiInner<T, P, R>,
+}
+
+impl<T, P, R> NodeCacheNetApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    pub fn new(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> Self {
+        Self { inner: build_inner(provider, recorder, config) }
+    }
+}
+
+impl<T, P, R> NetApiServer for NodeCacheNetApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    fn version(&self) -> RpcResult<String> {
+        let chain_id = tokio::task::block_in_place(move || Handle::current().block_on(self.inner.chain_id()))?;
+        Ok(chain_id.to_string())
+    }
+
+    fn peer_count(&self) -> RpcResult<U64> {
+        Ok(U64::ZERO)
+    }
+
+    fn is_listening(&self) -> RpcResult<bool> {
+        Ok(true)
+    }
+}
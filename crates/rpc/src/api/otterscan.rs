@@ -0,0 +1,293 @@
+use crate::config::NodeCacheConfig;
+use crate::helper::base::{build_inner, ApiInner};
+use crate::helper::block::{is_fixed_block, BlockKey};
+use crate::helper::error::{alloy_error, eyre_error, not_recorded_error};
+use alloy_primitives::{Address, Bytes, TxHash, B256};
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{BlockId, BlockNumberOrTag, Header, Transaction};
+use alloy_rpc_types_trace::otterscan::{
+    BlockDetails, ContractCreator, InternalOperation, OtsBlockTransactions, TraceEntry, TransactionsWithReceipts,
+};
+use alloy_transport::Transport;
+use async_trait::async_trait;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use reth_rpc_api::OtterscanServer;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub const OTS_HAS_CODE_KEY_PREFIX: &str = "ots_has_code";
+pub const OTS_GET_INTERNAL_OPERATIONS_KEY_PREFIX: &str = "ots_get_internal_operations";
+pub const OTS_GET_TRANSACTION_ERROR_KEY_PREFIX: &str = "ots_get_transaction_error";
+pub const OTS_TRACE_TRANSACTION_KEY_PREFIX: &str = "ots_trace_transaction";
+pub const OTS_GET_BLOCK_DETAILS_KEY_PREFIX: &str = "ots_get_block_details";
+pub const OTS_GET_BLOCK_DETAILS_BY_HASH_KEY_PREFIX: &str = "ots_get_block_details_by_hash";
+pub const OTS_GET_BLOCK_TRANSACTIONS_KEY_PREFIX: &str = "ots_get_block_transactions";
+pub const OTS_SEARCH_TRANSACTIONS_BEFORE_KEY_PREFIX: &str = "ots_search_transactions_before";
+pub const OTS_SEARCH_TRANSACTIONS_AFTER_KEY_PREFIX: &str = "ots_search_transactions_after";
+pub const OTS_GET_TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX: &str = "ots_get_transaction_by_sender_and_nonce";
+pub const OTS_GET_CONTRACT_CREATOR_KEY_PREFIX: &str = "ots_get_contract_creator";
+
+/// Otterscan API level supported by node cache
+const API_LEVEL: u64 = 8;
+
+pub struct NodeCacheOtterscanApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    inner: ApiInner<T, P, R>,
+}
+
+impl<T, P, R> NodeCacheOtterscanApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    pub fn new(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> Self {
+        Self { inner: build_inner(provider, recorder, config) }
+    }
+}
+
+#[async_trait]
+impl<T, P, R> OtterscanServer<Transaction, Header> for NodeCacheOtterscanApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    async fn get_header_by_number(&self, block_number: u64) -> RpcResult<Option<Header>> {
+        let block = self.inner.get_block(BlockKey::Number(BlockNumberOrTag::Number(block_number)), false).await?;
+        Ok(block.map(|block| block.header))
+    }
+
+    async fn has_code(&self, address: Address, block_id: Option<BlockId>) -> RpcResult<bool> {
+        let block_id = self.inner.pin_block_id(block_id).await?;
+        let key = format!("{}{}{:?}", OTS_HAS_CODE_KEY_PREFIX, address, block_id);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: bool = provider.raw_request("ots_hasCode".into(), (address, block_id)).await.map_err(alloy_error)?;
+            // code can be deployed or destroyed at the next block
+            if block_id.is_some_and(is_fixed_block) {
+                self.inner.record_for_block(block_id, &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_hasCode"))
+    }
+
+    async fn get_api_level(&self) -> RpcResult<u64> {
+        Ok(API_LEVEL)
+    }
+
+    async fn get_internal_operations(&self, tx_hash: TxHash) -> RpcResult<Vec<InternalOperation>> {
+        self.inner.check_fork_transaction_hash(tx_hash).await?;
+        let key = format!("{}{}", OTS_GET_INTERNAL_OPERATIONS_KEY_PREFIX, tx_hash);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Vec<InternalOperation> =
+                provider.raw_request("ots_getInternalOperations".into(), (tx_hash,)).await.map_err(alloy_error)?;
+            self.inner.record_for_transaction(tx_hash, &key, &result, started).await?;
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_getInternalOperations"))
+    }
+
+    async fn get_transaction_error(&self, tx_hash: TxHash) -> RpcResult<Option<Bytes>> {
+        self.inner.check_fork_transaction_hash(tx_hash).await?;
+        let key = format!("{}{}", OTS_GET_TRANSACTION_ERROR_KEY_PREFIX, tx_hash);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Bytes> = provider.raw_request("ots_getTransactionError".into(), (tx_hash,)).await.map_err(alloy_error)?;
+            self.inner.record_for_transaction(tx_hash, &key, &result, started).await?;
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_getTransactionError"))
+    }
+
+    async fn trace_transaction(&self, tx_hash: TxHash) -> RpcResult<Option<Vec<TraceEntry>>> {
+        self.inner.check_fork_transaction_hash(tx_hash).await?;
+        let key = format!("{}{}", OTS_TRACE_TRANSACTION_KEY_PREFIX, tx_hash);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Vec<TraceEntry>> =
+                provider.raw_request("ots_traceTransaction".into(), (tx_hash,)).await.map_err(alloy_error)?;
+            self.inner.record_for_transaction(tx_hash, &key, &result, started).await?;
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    async fn get_block_details(&self, block_number: u64) -> RpcResult<BlockDetails> {
+        self.inner.check_fork_block(block_number)?;
+        let key = format!("{}{}", OTS_GET_BLOCK_DETAILS_KEY_PREFIX, block_number);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: BlockDetails = provider.raw_request("ots_getBlockDetails".into(), (block_number,)).await.map_err(alloy_error)?;
+            self.inner.record_for_block(Some(BlockId::number(block_number)), &key, &result, started).await?;
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_getBlockDetails"))
+    }
+
+    async fn get_block_details_by_hash(&self, block_hash: B256) -> RpcResult<BlockDetails> {
+        let key = format!("{}{}", OTS_GET_BLOCK_DETAILS_BY_HASH_KEY_PREFIX, block_hash);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: BlockDetails =
+                provider.raw_request("ots_getBlockDetailsByHash".into(), (block_hash,)).await.map_err(alloy_error)?;
+            self.inner.record_for_block(Some(BlockId::hash(block_hash)), &key, &result, started).await?;
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_getBlockDetailsByHash"))
+    }
+
+    async fn get_block_transactions(&self, block_number: u64, page_number: usize, page_size: usize) -> RpcResult<OtsBlockTransactions> {
+        self.inner.check_fork_block(block_number)?;
+        let key = format!("{}{}{}{}", OTS_GET_BLOCK_TRANSACTIONS_KEY_PREFIX, block_number, page_number, page_size);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: OtsBlockTransactions = provider
+                .raw_request("ots_getBlockTransactions".into(), (block_number, page_number, page_size))
+                .await
+                .map_err(alloy_error)?;
+            self.inner.record_for_block(Some(BlockId::number(block_number)), &key, &result, started).await?;
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_getBlockTransactions"))
+    }
+
+    async fn search_transactions_before(
+        &self,
+        address: Address,
+        block_number: u64,
+        page_size: usize,
+    ) -> RpcResult<TransactionsWithReceipts> {
+        let key = format!("{}{}{}{}", OTS_SEARCH_TRANSACTIONS_BEFORE_KEY_PREFIX, address, block_number, page_size);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: TransactionsWithReceipts = provider
+                .raw_request("ots_searchTransactionsBefore".into(), (address, block_number, page_size))
+                .await
+                .map_err(alloy_error)?;
+            // the first page contains the newest transactions and grows with the chain
+            let newest = result.txs.iter().filter_map(|transaction| transaction.block_number).max();
+            if let (false, Some(newest)) = (result.first_page, newest) {
+                self.inner.record_for_block(Some(BlockId::number(newest)), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_searchTransactionsBefore"))
+    }
+
+    async fn search_transactions_after(
+        &self,
+        address: Address,
+        block_number: u64,
+        page_size: usize,
+    ) -> RpcResult<TransactionsWithReceipts> {
+        let key = format!("{}{}{}{}", OTS_SEARCH_TRANSACTIONS_AFTER_KEY_PREFIX, address, block_number, page_size);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: TransactionsWithReceipts = provider
+                .raw_request("ots_searchTransactionsAfter".into(), (address, block_number, page_size))
+                .await
+                .map_err(alloy_error)?;
+            // the first page contains the newest transactions and grows with the chain
+            let newest = result.txs.iter().filter_map(|transaction| transaction.block_number).max();
+            if let (false, Some(newest)) = (result.first_page, newest) {
+                self.inner.record_for_block(Some(BlockId::number(newest)), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Err(not_recorded_error("ots_searchTransactionsAfter"))
+    }
+
+    async fn get_transaction_by_sender_and_nonce(&self, sender: Address, nonce: u64) -> RpcResult<Option<TxHash>> {
+        let key = format!("{}{}{}", OTS_GET_TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX, sender, nonce);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(Some(ret));
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<TxHash> =
+                provider.raw_request("ots_getTransactionBySenderAndNonce".into(), (sender, nonce)).await.map_err(alloy_error)?;
+            // the nonce might still be used later
+            if let Some(ref tx_hash) = result {
+                self.inner.record_for_transaction(*tx_hash, &key, tx_hash, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    async fn get_contract_creator(&self, address: Address) -> RpcResult<Option<ContractCreator>> {
+        let key = format!("{}{}", OTS_GET_CONTRACT_CREATOR_KEY_PREFIX, address);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(Some(ret));
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<ContractCreator> =
+                provider.raw_request("ots_getContractCreator".into(), (address,)).await.map_err(alloy_error)?;
+            // the contract might still be deployed later
+            if let Some(ref creator) = result {
+                self.inner.record_for_transaction(creator.hash, &key, creator, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,229 @@
+use crate::config::NodeCacheConfig;
+use crate::helper::base::{build_inner, ApiInner};
+use crate::helper::block::is_fixed_block;
+use crate::helper::call::state_override_key;
+use crate::helper::error::{alloy_error, eyre_error, unsupported_error};
+use crate::helper::trace::trace_types_key;
+use alloy_primitives::map::HashSet;
+use alloy_primitives::{Bytes, B256};
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::state::StateOverride;
+use alloy_rpc_types_eth::{BlockId, BlockOverrides, Index, TransactionRequest};
+use alloy_rpc_types_trace::filter::TraceFilter;
+use alloy_rpc_types_trace::opcode::{BlockOpcodeGas, TransactionOpcodeGas};
+use alloy_rpc_types_trace::parity::{LocalizedTransactionTrace, TraceResults, TraceResultsWithTransactionHash, TraceType};
+use alloy_transport::Transport;
+use async_trait::async_trait;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use reth_rpc_api::TraceApiServer;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub const TRACE_CALL_KEY_PREFIX: &str = "trace_call";
+pub const TRACE_REPLAY_BLOCK_TRANSACTIONS_KEY_PREFIX: &str = "trace_replay_block_transactions";
+pub const TRACE_REPLAY_TRANSACTION_KEY_PREFIX: &str = "trace_replay_transaction";
+pub const TRACE_BLOCK_KEY_PREFIX: &str = "trace_block";
+pub const TRACE_FILTER_KEY_PREFIX: &str = "trace_filter";
+pub const TRACE_TRANSACTION_KEY_PREFIX: &str = "trace_transaction";
+
+pub struct NodeCacheTraceApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    inner: ApiInner<T, P, R>,
+}
+
+impl<T, P, R> NodeCacheTraceApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    pub fn new(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> Self {
+        Self { inner: build_inner(provider, recorder, config) }
+    }
+}
+
+fn empty_trace_results() -> TraceResults {
+    TraceResults { output: Bytes::new(), state_diff: None, trace: vec![], vm_trace: None }
+}
+
+#[async_trait]
+impl<T, P, R> TraceApiServer for NodeCacheTraceApi<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    async fn trace_call(
+        &self,
+        call: TransactionRequest,
+        trace_types: HashSet<TraceType>,
+        block_id: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<Box<BlockOverrides>>,
+    ) -> RpcResult<TraceResults> {
+        let block_id = self.inner.pin_block_id(block_id).await?;
+        let key = format!(
+            "{}{:?}{}{:?}{}{:?}",
+            TRACE_CALL_KEY_PREFIX,
+            call,
+            trace_types_key(&trace_types),
+            block_id,
+            state_override_key(state_overrides.as_ref()),
+            block_overrides
+        );
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: TraceResults = provider
+                .raw_request("trace_call".into(), (call, trace_types, block_id, state_overrides, block_overrides))
+                .await
+                .map_err(alloy_error)?;
+            // calls on top of a moving tag change with the chain
+            if block_id.is_some_and(is_fixed_block) {
+                self.inner.record_for_block(block_id, &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(empty_trace_results())
+    }
+
+    async fn trace_call_many(
+        &self,
+        _calls: Vec<(TransactionRequest, HashSet<TraceType>)>,
+        _block_id: Option<BlockId>,
+    ) -> RpcResult<Vec<TraceResults>> {
+        Err(unsupported_error("trace_callMany"))
+    }
+
+    async fn trace_raw_transaction(
+        &self,
+        _data: Bytes,
+        _trace_types: HashSet<TraceType>,
+        _block_id: Option<BlockId>,
+    ) -> RpcResult<TraceResults> {
+        Err(unsupported_error("trace_rawTransaction"))
+    }
+
+    async fn replay_block_transactions(
+        &self,
+        block_id: BlockId,
+        trace_types: HashSet<TraceType>,
+    ) -> RpcResult<Option<Vec<TraceResultsWithTransactionHash>>> {
+        let block_id = self.inner.pin_block_id(Some(block_id)).await?.unwrap_or(block_id);
+        let key = format!("{}{}{}", TRACE_REPLAY_BLOCK_TRANSACTIONS_KEY_PREFIX, block_id, trace_types_key(&trace_types));
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Vec<TraceResultsWithTransactionHash>> =
+                provider.raw_request("trace_replayBlockTransactions".into(), (block_id, trace_types)).await.map_err(alloy_error)?;
+            if is_fixed_block(block_id) {
+                self.inner.record_for_block(Some(block_id), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    async fn replay_transaction(&self, transaction: B256, trace_types: HashSet<TraceType>) -> RpcResult<TraceResults> {
+        self.inner.check_fork_transaction_hash(transaction).await?;
+        let key = format!("{}{}{}", TRACE_REPLAY_TRANSACTION_KEY_PREFIX, transaction, trace_types_key(&trace_types));
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: TraceResults =
+                provider.raw_request("trace_replayTransaction".into(), (transaction, trace_types)).await.map_err(alloy_error)?;
+            self.inner.record_for_transaction(transaction, &key, &result, started).await?;
+            return Ok(result);
+        }
+        Ok(empty_trace_results())
+    }
+
+    async fn trace_block(&self, block_id: BlockId) -> RpcResult<Option<Vec<LocalizedTransactionTrace>>> {
+        let block_id = self.inner.pin_block_id(Some(block_id)).await?.unwrap_or(block_id);
+        let key = format!("{}{}", TRACE_BLOCK_KEY_PREFIX, block_id);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Vec<LocalizedTransactionTrace>> =
+                provider.raw_request("trace_block".into(), (block_id,)).await.map_err(alloy_error)?;
+            if is_fixed_block(block_id) {
+                self.inner.record_for_block(Some(block_id), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    async fn trace_filter(&self, filter: TraceFilter) -> RpcResult<Vec<LocalizedTransactionTrace>> {
+        let key = format!("{}{:?}", TRACE_FILTER_KEY_PREFIX, filter);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let to_block = filter.to_block;
+            let result: Vec<LocalizedTransactionTrace> =
+                provider.raw_request("trace_filter".into(), (filter,)).await.map_err(alloy_error)?;
+            // without an end block new blocks add more traces
+            if let Some(to_block) = to_block {
+                self.inner.record_for_block(Some(BlockId::number(to_block)), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(vec![])
+    }
+
+    async fn trace_get(&self, _hash: B256, _indices: Vec<Index>) -> RpcResult<Option<LocalizedTransactionTrace>> {
+        Err(unsupported_error("trace_get"))
+    }
+
+    async fn trace_transaction(&self, hash: B256) -> RpcResult<Option<Vec<LocalizedTransactionTrace>>> {
+        self.inner.check_fork_transaction_hash(hash).await?;
+        let key = format!("{}{}", TRACE_TRANSACTION_KEY_PREFIX, hash);
+        // cache
+        if let Some(ret) = self.inner.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.inner.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Vec<LocalizedTransactionTrace>> =
+                provider.raw_request("trace_transaction".into(), (hash,)).await.map_err(alloy_error)?;
+            self.inner.record_for_transaction(hash, &key, &result, started).await?;
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    async fn trace_transaction_opcode_gas(&self, _tx_hash: B256) -> RpcResult<Option<TransactionOpcodeGas>> {
+        Err(unsupported_error("trace_transactionOpcodeGas"))
+    }
+
+    async fn trace_block_opcode_gas(&self, _block_id: BlockId) -> RpcResult<Option<BlockOpcodeGas>> {
+        Err(unsupported_error("trace_blockOpcodeGas"))
+    }
+}
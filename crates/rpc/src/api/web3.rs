@@ -0,0 +1,52 @@
+use crate::config::NodeCacheConfig;
+use crate::helper::base::{build_inner, ApiInner};
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_transport::Transport;
+use async_trait::async_trait;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use reth_rpc_api::Web3ApiServer;
+use std::sync::Arc;
+
+pub struct NodeCacheWeb3Api<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    inner: ApiInner<T, P, R>,
+}
+
+impl<T, P, R> NodeCacheWeb3Api<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    pub fn new(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> Self {
+        Self { inner: build_inner(provider, recorder, config) }
+    }
+}
+
+#[async_trait]
+impl<T, P, R> Web3ApiServer for NodeCacheWeb3Api<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    async fn client_version(&self) -> RpcResult<String> {
+        let node_cache_version = format!("node-cache/v{}", env!("CARGO_PKG_VERSION"));
+        // version of the node the cache was recorded from
+        match self.inner.config.client_version.as_ref() {
+            Some(upstream_version) => Ok(format!("{} ({})", node_cache_version, upstream_version)),
+            None => Ok(node_cache_version),
+        }
+    }
+
+    fn sha3(&self, input: Bytes) -> RpcResult<B256> {
+        Ok(keccak256(input))
+    }
+}
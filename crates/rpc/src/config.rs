@@ -0,0 +1,166 @@
+use alloy_primitives::{Address, U64};
+use futures::lock::{Mutex, MutexGuard};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Slot time of the beacon chain
+const DEFAULT_BLOCK_TIME: Duration = Duration::from_secs(12);
+
+/// Options shared by all node cache APIs
+#[derive(Debug, Clone, Default)]
+pub struct NodeCacheConfig {
+    /// Seed `eth_getStorageAt` entries from `debug_storageRangeAt` results
+    pub seed_storage: bool,
+    /// What to do with transactions sent to node cache
+    pub submission_policy: SubmissionPolicy,
+    /// Answer `eth_getTransactionBySenderAndNonce` from cached blocks if the provider does not support it
+    pub sender_nonce_fallback: bool,
+    /// Answers for the node status methods without a provider
+    pub node_status: NodeStatus,
+    /// Only record blocks that can no longer be reorged, everything is recorded if not set
+    pub finality: Option<Finality>,
+    /// Block treated as the latest block, newer blocks are rejected
+    pub fork_block: Option<u64>,
+    /// Time between two blocks of the upstream, spot prices and the finalized head are reused for that long, one slot if not set
+    pub block_time: Option<Duration>,
+    /// Record the responses of time-varying methods in call order to replay them in the same order
+    pub record_sequences: bool,
+    /// Answer state requests from values recorded at other blocks if no block in between changed them
+    pub derive_state: bool,
+    /// Verify responses against block hashes, roots and proofs before recording them
+    pub verify: bool,
+    /// Upstream the responses are recorded from, without credentials
+    pub upstream: Option<String>,
+    /// Client version of the upstream, fetched once at startup or read from the recording without a provider
+    pub client_version: Option<String>,
+    /// Responses of the upstream which failed verification, shared by all APIs
+    pub upstream_health: UpstreamHealth,
+    /// Serializes the updates of index entries, shared by all APIs
+    pub index_lock: IndexLock,
+}
+
+impl NodeCacheConfig {
+    pub fn block_time(&self) -> Duration {
+        self.block_time.unwrap_or(DEFAULT_BLOCK_TIME)
+    }
+}
+
+/// Counts the responses of the upstream which failed verification
+#[derive(Debug, Clone, Default)]
+pub struct UpstreamHealth {
+    misbehaviours: Arc<AtomicU64>,
+}
+
+impl UpstreamHealth {
+    /// Count a misbehaviour and return the total so far
+    pub fn report_misbehaviour(&self) -> u64 {
+        self.misbehaviours.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn misbehaviours(&self) -> u64 {
+        self.misbehaviours.load(Ordering::Relaxed)
+    }
+}
+
+/// Index entries list other keys, they are read, extended and recorded again under the lock
+#[derive(Debug, Clone, Default)]
+pub struct IndexLock {
+    lock: Arc<Mutex<()>>,
+}
+
+impl IndexLock {
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finality {
+    /// Blocks at or below the finalized block of the provider
+    Finalized,
+    /// Blocks with at least this many blocks on top
+    Confirmations(u64),
+}
+
+impl FromStr for Finality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "finalized" => Ok(Finality::Finalized),
+            _ => s
+                .parse()
+                .map(Finality::Confirmations)
+                .map_err(|_| format!("Unknown finality: {}, expected finalized or a number of confirmations", s)),
+        }
+    }
+}
+
+/// Node cache without a provider is never syncing or mining
+#[derive(Debug, Clone)]
+pub struct NodeStatus {
+    pub protocol_version: U64,
+    pub accounts: Vec<Address>,
+    pub author: Address,
+}
+
+impl Default for NodeStatus {
+    fn default() -> Self {
+        // eth/68
+        NodeStatus { protocol_version: U64::from(68), accounts: vec![], author: Address::ZERO }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubmissionPolicy {
+    /// Send transactions and signing requests to the real provider
+    Forward,
+    /// Refuse all transactions and signing requests
+    #[default]
+    Reject,
+    /// Return the transaction hash and keep the transaction as pending without sending it
+    Mock,
+}
+
+impl FromStr for SubmissionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(SubmissionPolicy::Forward),
+            "reject" => Ok(SubmissionPolicy::Reject),
+            "mock" => Ok(SubmissionPolicy::Mock),
+            _ => Err(format!("Unknown submission policy: {}, expected forward, reject or mock", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_submission_policies() {
+        assert_eq!("forward".parse::<SubmissionPolicy>(), Ok(SubmissionPolicy::Forward));
+        assert_eq!("reject".parse::<SubmissionPolicy>(), Ok(SubmissionPolicy::Reject));
+        assert_eq!("mock".parse::<SubmissionPolicy>(), Ok(SubmissionPolicy::Mock));
+        assert!("Forward".parse::<SubmissionPolicy>().is_err());
+    }
+
+    #[test]
+    fn parses_finality() {
+        assert_eq!("finalized".parse::<Finality>(), Ok(Finality::Finalized));
+        assert_eq!("12".parse::<Finality>(), Ok(Finality::Confirmations(12)));
+        assert!("safe".parse::<Finality>().is_err());
+        assert!("-1".parse::<Finality>().is_err());
+    }
+
+    #[test]
+    fn block_time_defaults_to_one_slot() {
+        assert_eq!(NodeCacheConfig::default().block_time(), Duration::from_secs(12));
+        let config = NodeCacheConfig { block_time: Some(Duration::from_secs(2)), ..Default::default() };
+        assert_eq!(config.block_time(), Duration::from_secs(2));
+    }
+}
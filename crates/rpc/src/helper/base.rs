@@ -1,16 +1,36 @@
+use crate::config::NodeCacheConfig;
+use crate::helper::error::{alloy_error, eyre_error};
 use alloy_provider::network::Ethereum;
 use alloy_provider::Provider;
 use alloy_transport::Transport;
-use node_cache_recorder::Recorder;
-use std::sync::Arc;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::{Provenance, Recorder};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
 
-pub fn build_inner<T, P, R>(provider: Option<P>, recorder: Arc<R>) -> ApiInner<T, P, R>
+/// Version of the key format, increased whenever the keys or values of recorded entries change
+pub const KEY_SCHEMA_VERSION: u32 = 1;
+
+pub const CHAIN_ID_KEY: &str = "chain_id";
+pub const CLIENT_VERSION_KEY: &str = "client_version";
+
+pub fn build_inner<T, P, R>(provider: Option<P>, recorder: Arc<R>, config: NodeCacheConfig) -> ApiInner<T, P, R>
 where
     T: Transport + Clone,
     P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
     R: Recorder,
 {
-    ApiInner { provider, _t: std::marker::PhantomData, recorder }
+    ApiInner {
+        provider,
+        _t: std::marker::PhantomData,
+        recorder,
+        config,
+        finalized_head: Arc::new(Mutex::new(None)),
+        sequence_positions: Arc::new(Mutex::new(HashMap::new())),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +43,11 @@ where
     pub(crate) provider: Option<P>,
     _t: std::marker::PhantomData<T>,
     pub(crate) recorder: Arc<R>,
+    pub(crate) config: NodeCacheConfig,
+    /// Last finalized block number seen at the provider and when it was fetched
+    pub(crate) finalized_head: Arc<Mutex<Option<(Instant, u64)>>>,
+    /// Number of calls per time-varying method, to record and replay them in order
+    pub(crate) sequence_positions: Arc<Mutex<HashMap<&'static str, usize>>>,
 }
 
 impl<T, P, R> ApiInner<T, P, R>
@@ -31,4 +56,113 @@ where
     P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
     R: Recorder,
 {
+    pub async fn chain_id(&self) -> RpcResult<u64> {
+        // cache
+        if let Some(ret) = self.recorder.get(CHAIN_ID_KEY).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.provider.as_ref() {
+            let started = Instant::now();
+            let result = provider.get_chain_id().await.map_err(alloy_error)?;
+            self.record(CHAIN_ID_KEY, &result, started).await?;
+            return Ok(result);
+        }
+        // mainnet, if nothing was recorded
+        Ok(1)
+    }
+
+    /// Where, when and how fast an upstream response was fetched
+    pub fn provenance(&self, started: Instant) -> Provenance {
+        Provenance {
+            recorded_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default(),
+            upstream: self.config.upstream.clone(),
+            client_version: self.config.client_version.clone(),
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            node_cache_version: env!("CARGO_PKG_VERSION").to_string(),
+            key_schema_version: KEY_SCHEMA_VERSION,
+        }
+    }
+
+    /// Record an upstream response together with its provenance
+    pub async fn record<V: Serialize + Send + Sync>(&self, key: &str, value: &V, started: Instant) -> RpcResult<()> {
+        self.record_with(key, value, &self.provenance(started)).await
+    }
+
+    /// Add the key to the keys listed by the index entry
+    pub async fn add_to_index(&self, index_key: &str, key: &str, provenance: Option<&Provenance>) -> RpcResult<()> {
+        let _guard = self.config.index_lock.lock().await;
+        let mut keys: Vec<String> = self.recorder.peek(index_key).await.map_err(eyre_error)?.unwrap_or_default();
+        if keys.iter().any(|indexed| indexed == key) {
+            return Ok(());
+        }
+        keys.push(key.to_string());
+        match provenance {
+            Some(provenance) => self.record_with(index_key, &keys, provenance).await,
+            None => self.recorder.record(index_key, &keys).await.map_err(eyre_error),
+        }
+    }
+
+    /// Record an entry derived from an upstream response with the provenance of that response
+    pub async fn record_with<V: Serialize + Send + Sync>(&self, key: &str, value: &V, provenance: &Provenance) -> RpcResult<()> {
+        self.recorder.record_with_provenance(key, value, provenance).await.map_err(eyre_error)
+    }
+}
+
+/// Client version of the upstream, fetched once and recorded to answer `web3_clientVersion` without a provider
+pub async fn client_version<T, P, R>(provider: Option<&P>, recorder: &R) -> eyre::Result<Option<String>>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum>,
+    R: Recorder + Sync,
+{
+    let Some(provider) = provider else {
+        return recorder.get(CLIENT_VERSION_KEY).await;
+    };
+    match provider.get_client_version().await {
+        Ok(client_version) => {
+            recorder.record(CLIENT_VERSION_KEY, &client_version).await?;
+            Ok(Some(client_version))
+        }
+        Err(err) => {
+            warn!("Can not fetch the client version of the upstream: {}", err);
+            recorder.get(CLIENT_VERSION_KEY).await
+        }
+    }
+}
+
+/// Record an entry derived from a recorded entry, it keeps the provenance of the source
+pub async fn record_derived<R: Recorder + Sync, V: Serialize + Send + Sync>(
+    recorder: &R,
+    key: &str,
+    value: &V,
+    source: &str,
+) -> eyre::Result<()> {
+    match recorder.provenance(source).await? {
+        Some(provenance) => recorder.record_with_provenance(key, value, &provenance).await,
+        None => recorder.record(key, value).await,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy_provider::RootProvider;
+    use alloy_transport::BoxTransport;
+    use node_cache_recorder::MemoryRecorder;
+
+    pub(crate) type TestApiInner = ApiInner<BoxTransport, RootProvider<BoxTransport>, MemoryRecorder>;
+
+    /// Api without a provider, answering from an in-memory recording
+    pub(crate) fn test_inner(config: NodeCacheConfig) -> TestApiInner {
+        build_inner(None, Arc::new(MemoryRecorder::new()), config)
+    }
+
+    #[tokio::test]
+    async fn answers_the_recorded_chain_id() {
+        let inner = test_inner(NodeCacheConfig::default());
+        assert_eq!(inner.chain_id().await.unwrap(), 1);
+        inner.recorder.record(CHAIN_ID_KEY, &10u64).await.unwrap();
+        assert_eq!(inner.chain_id().await.unwrap(), 10);
+    }
 }
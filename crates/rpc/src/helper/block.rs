@@ -0,0 +1,396 @@
+use crate::helper::base::ApiInner;
+use crate::helper::error::{alloy_error, eyre_error};
+use alloy_consensus::Transaction as _;
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_provider::network::eip2718::Encodable2718;
+use alloy_provider::network::{Ethereum, TransactionResponse};
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{Block, BlockId, BlockNumberOrTag, Header, Index, Transaction};
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::{Provenance, Recorder};
+use std::time::Instant;
+
+pub const BLOCK_KEY_PREFIX: &str = "block_by_hash";
+pub const BLOCK_HASH_BY_NUMBER_KEY_PREFIX: &str = "block_hash_by_number";
+pub const UNCLE_KEY_PREFIX: &str = "uncle";
+pub const TRANSACTION_BY_HASH_KEY_PREFIX: &str = "transaction_by_hash";
+pub const TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX: &str = "transaction_by_block_hash_and_index";
+pub const TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX: &str = "transaction_by_block_number_and_index";
+pub const RAW_TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX: &str = "raw_transaction_by_block_hash_and_index";
+pub const RAW_TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX: &str = "raw_transaction_by_block_number_and_index";
+pub const SENDER_NONCE_KEY_PREFIX: &str = "sender_nonce";
+
+#[derive(Debug, Clone, Copy)]
+pub enum BlockKey {
+    Hash(B256),
+    Number(BlockNumberOrTag),
+}
+
+impl From<BlockKey> for BlockId {
+    fn from(block: BlockKey) -> Self {
+        match block {
+            BlockKey::Hash(hash) => BlockId::hash(hash),
+            BlockKey::Number(number) => BlockId::Number(number),
+        }
+    }
+}
+
+impl From<BlockId> for BlockKey {
+    fn from(block: BlockId) -> Self {
+        match block {
+            BlockId::Hash(hash) => BlockKey::Hash(hash.block_hash),
+            BlockId::Number(number) => BlockKey::Number(number),
+        }
+    }
+}
+
+pub fn block_key(hash: B256) -> String {
+    format!("{}{}", BLOCK_KEY_PREFIX, hash)
+}
+
+pub fn block_hash_key(number: BlockNumberOrTag) -> String {
+    format!("{}{}", BLOCK_HASH_BY_NUMBER_KEY_PREFIX, number)
+}
+
+pub fn uncle_key(hash: B256) -> String {
+    format!("{}{}", UNCLE_KEY_PREFIX, hash)
+}
+
+/// Blocks referenced by hash or number always return the same data, tags move with the chain
+pub fn is_fixed_block(block_id: BlockId) -> bool {
+    match block_id {
+        BlockId::Hash(_) => true,
+        BlockId::Number(number) => number.is_number() || number.is_earliest(),
+    }
+}
+
+pub fn transaction_key(hash: B256) -> String {
+    format!("{}{}", TRANSACTION_BY_HASH_KEY_PREFIX, hash)
+}
+
+/// Hash of the mined transaction of the sender with the nonce
+pub fn sender_nonce_key(sender: Address, nonce: u64) -> String {
+    format!("{}{}:{}", SENDER_NONCE_KEY_PREFIX, sender, nonce)
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Blocks are stored once by hash and numbers only point to the hash. A block with full transactions
+    /// also answers requests for transaction hashes only.
+    pub async fn get_block(&self, block: BlockKey, full: bool) -> RpcResult<Option<Block>> {
+        let block = self.pin_block_key(block)?;
+        // cache
+        if let Some(mut cached) = self.cached_block(block).await? {
+            if !full {
+                cached.transactions.convert_to_hashes();
+                return Ok(Some(cached));
+            }
+            if cached.transactions.is_full() || cached.transactions.is_empty() {
+                return Ok(Some(cached));
+            }
+        }
+        // real provider
+        if let Some(provider) = self.provider.as_ref() {
+            let started = Instant::now();
+            let result = match block {
+                BlockKey::Hash(hash) => provider.get_block_by_hash(hash, full.into()).await.map_err(alloy_error)?,
+                BlockKey::Number(number) => provider.get_block_by_number(number, full.into()).await.map_err(alloy_error)?,
+            };
+            if let Some(ref fetched) = result {
+                self.check_fork_block(fetched.header.number)?;
+                if self.verify_block(fetched) {
+                    self.record_block(block, fetched, &self.provenance(started)).await?;
+                }
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    /// Block for internal checks, a cached block is read without counting as a use of the entry
+    pub async fn lookup_block(&self, block: BlockKey, full: bool) -> RpcResult<Option<Block>> {
+        let block = self.pin_block_key(block)?;
+        if let Some(cached) = self.peek_block(block).await? {
+            if !full || cached.transactions.is_full() || cached.transactions.is_empty() {
+                return Ok(Some(cached));
+            }
+        }
+        self.get_block(block, full).await
+    }
+
+    pub async fn get_block_transaction(&self, block: BlockKey, index: Index) -> RpcResult<Option<Transaction>> {
+        let block = self.pin_block_key(block)?;
+        // cached full block
+        if let Some(cached) = self.cached_block(block).await? {
+            if let Some(transactions) = cached.transactions.as_transactions() {
+                return Ok(transactions.get(usize::from(index)).cloned());
+            }
+        }
+        let key = match block {
+            BlockKey::Hash(hash) => format!("{}{}{}", TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX, hash, usize::from(index)),
+            BlockKey::Number(number) => format!("{}{}{}", TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX, number, usize::from(index)),
+        };
+        // cache
+        if let Some(ret) = self.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Transaction> = match block {
+                BlockKey::Hash(hash) => {
+                    provider.raw_request("eth_getTransactionByBlockHashAndIndex".into(), (hash, index)).await.map_err(alloy_error)?
+                }
+                BlockKey::Number(number) => {
+                    provider.raw_request("eth_getTransactionByBlockNumberAndIndex".into(), (number, index)).await.map_err(alloy_error)?
+                }
+            };
+            if let Some(number) = result.as_ref().and_then(|transaction| transaction.block_number) {
+                self.check_fork_block(number)?;
+            }
+            let verified = match result {
+                Some(ref transaction) => self.verify_transaction(transaction).await?,
+                None => !self.config.verify,
+            };
+            if verified && self.is_final(Some(block.into())).await? {
+                let provenance = self.provenance(started);
+                self.record_with(&key, &result, &provenance).await?;
+                self.index_block_entry(Some(block.into()), &key).await?;
+                if let Some(ref transaction) = result {
+                    let hash_key = transaction_key(transaction.tx_hash());
+                    self.record_with(&hash_key, transaction, &provenance).await?;
+                    self.index_transaction_entry(transaction, &hash_key).await?;
+                }
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    pub async fn get_raw_block_transaction(&self, block: BlockKey, index: Index) -> RpcResult<Option<Bytes>> {
+        let block = self.pin_block_key(block)?;
+        // cached full block
+        if let Some(cached) = self.cached_block(block).await? {
+            if let Some(transactions) = cached.transactions.as_transactions() {
+                return Ok(transactions.get(usize::from(index)).map(|transaction| transaction.inner.encoded_2718().into()));
+            }
+        }
+        let key = match block {
+            BlockKey::Hash(hash) => format!("{}{}{}", RAW_TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX, hash, usize::from(index)),
+            BlockKey::Number(number) => format!("{}{}{}", RAW_TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX, number, usize::from(index)),
+        };
+        // cache
+        if let Some(ret) = self.recorder.get(&key).await.map_err(eyre_error)? {
+            return Ok(ret);
+        }
+        // real provider
+        if let Some(provider) = self.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Bytes> = match block {
+                BlockKey::Hash(hash) => {
+                    provider.raw_request("eth_getRawTransactionByBlockHashAndIndex".into(), (hash, index)).await.map_err(alloy_error)?
+                }
+                BlockKey::Number(number) => {
+                    provider.raw_request("eth_getRawTransactionByBlockNumberAndIndex".into(), (number, index)).await.map_err(alloy_error)?
+                }
+            };
+            // raw bytes do not contain the block number
+            if self.config.fork_block.is_some() {
+                self.lookup_block(block, false).await?;
+            }
+            let verified = match result {
+                Some(ref raw) => self.verify_raw_transaction(block, usize::from(index), raw).await?,
+                None => !self.config.verify,
+            };
+            if verified {
+                self.record_for_block(Some(block.into()), &key, &result, started).await?;
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    /// Uncles are found by the uncle hashes of the block, so an uncle is stored once by its own hash
+    pub async fn get_uncle(&self, block: BlockKey, index: Index) -> RpcResult<Option<Block>> {
+        let Some(block) = self.get_block(block, false).await? else {
+            return Ok(None);
+        };
+        let Some(uncle_hash) = block.uncles.get(usize::from(index)).copied() else {
+            return Ok(None);
+        };
+        // cache
+        if let Some(ret) = self.recorder.get(&uncle_key(uncle_hash)).await.map_err(eyre_error)? {
+            return Ok(Some(ret));
+        }
+        // real provider
+        if let Some(provider) = self.provider.as_ref() {
+            let started = Instant::now();
+            let result: Option<Block> =
+                provider.raw_request("eth_getUncleByBlockHashAndIndex".into(), (block.header.hash, index)).await.map_err(alloy_error)?;
+            if let Some(ref uncle) = result {
+                if self.verify_uncle(uncle, uncle_hash) {
+                    self.record(&uncle_key(uncle_hash), uncle, started).await?;
+                }
+            }
+            return Ok(result);
+        }
+        Ok(None)
+    }
+
+    /// Transaction of the sender with the nonce from the cached full blocks
+    pub async fn cached_sender_transaction(&self, sender: Address, nonce: u64) -> RpcResult<Option<Transaction>> {
+        let Some(hash) = self.recorder.peek::<B256>(&sender_nonce_key(sender, nonce)).await.map_err(eyre_error)? else {
+            return Ok(None);
+        };
+        let transaction: Option<Transaction> = self.recorder.get(&transaction_key(hash)).await.map_err(eyre_error)?;
+        self.check_fork_transaction(transaction.as_ref())?;
+        Ok(transaction)
+    }
+
+    /// Header the spot price methods are derived from without a provider
+    pub async fn pinned_header(&self) -> RpcResult<Option<Header>> {
+        let latest = self.pin_block_key(BlockKey::Number(BlockNumberOrTag::Latest))?;
+        Ok(self.cached_block(latest).await?.map(|block| block.header))
+    }
+
+    async fn cached_block(&self, block: BlockKey) -> RpcResult<Option<Block>> {
+        let hash = match block {
+            BlockKey::Hash(hash) => hash,
+            BlockKey::Number(number) => match self.recorder.get::<B256>(&block_hash_key(number)).await.map_err(eyre_error)? {
+                Some(hash) => hash,
+                None => return Ok(None),
+            },
+        };
+        let cached: Option<Block> = self.recorder.get(&block_key(hash)).await.map_err(eyre_error)?;
+        if let Some(ref cached) = cached {
+            self.check_fork_block(cached.header.number)?;
+        }
+        Ok(cached)
+    }
+
+    async fn peek_block(&self, block: BlockKey) -> RpcResult<Option<Block>> {
+        let hash = match block {
+            BlockKey::Hash(hash) => hash,
+            BlockKey::Number(number) => match self.recorder.peek::<B256>(&block_hash_key(number)).await.map_err(eyre_error)? {
+                Some(hash) => hash,
+                None => return Ok(None),
+            },
+        };
+        let cached: Option<Block> = self.recorder.peek(&block_key(hash)).await.map_err(eyre_error)?;
+        if let Some(ref cached) = cached {
+            self.check_fork_block(cached.header.number)?;
+        }
+        Ok(cached)
+    }
+
+    /// The block, its transactions and the number and tag pointers share the provenance of the fetched block
+    async fn record_block(&self, block: BlockKey, fetched: &Block, provenance: &Provenance) -> RpcResult<()> {
+        // blocks which can still be reorged are served uncached
+        if !self.is_final_number(fetched.header.number).await? {
+            return Ok(());
+        }
+        let hash = fetched.header.hash;
+        self.check_reorg(fetched.header.number, hash).await?;
+        // never replace a block with full transactions by one with hashes only
+        let cached_full = match self.recorder.peek::<Block>(&block_key(hash)).await.map_err(eyre_error)? {
+            Some(cached) => cached.transactions.is_full(),
+            None => false,
+        };
+        if fetched.transactions.is_full() || !cached_full {
+            self.record_with(&block_key(hash), fetched, provenance).await?;
+        }
+        for transaction in fetched.transactions.txns() {
+            self.record_with(&transaction_key(transaction.tx_hash()), transaction, provenance).await?;
+            if self.config.sender_nonce_fallback {
+                let key = sender_nonce_key(transaction.from, transaction.inner.nonce());
+                self.record_with(&key, &transaction.tx_hash(), provenance).await?;
+            }
+        }
+        self.record_with(&block_hash_key(BlockNumberOrTag::Number(fetched.header.number)), &hash, provenance).await?;
+        if let BlockKey::Number(tag) = block {
+            if !tag.is_number() && self.config.finality.is_none() {
+                self.record_with(&block_hash_key(tag), &hash, provenance).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NodeCacheConfig;
+    use crate::helper::base::tests::test_inner;
+    use alloy_rpc_types_eth::BlockTransactions;
+
+    fn block(number: u64) -> Block {
+        let hash = B256::repeat_byte(number as u8);
+        let transaction: Transaction = serde_json::from_value(serde_json::json!({
+            "blockHash": hash,
+            "blockNumber": format!("{:#x}", number),
+            "hash": "0xe9e91f1ee4b56c0df2e9f06c2b8c27c6076195a88a7b8537ba8313d80e6f124e",
+            "transactionIndex": "0x0",
+            "type": "0x0",
+            "nonce": "0x43eb",
+            "input": "0x",
+            "r": "0x3b08715b4403c792b8c7567edea634088bedcd7f60d9352b1f16c69830f3afd5",
+            "s": "0x10b9afb67d2ec8b956f0e1dbc07eb79152904f3a7bf789fc869db56320adfe09",
+            "chainId": "0x0",
+            "v": "0x1c",
+            "gas": "0xc350",
+            "from": "0x32be343b94f860124dc4fee278fdcbd38c102d88",
+            "to": "0xdf190dc7190dfba737d7777a163445b7fff16133",
+            "value": "0x6113a84987be800",
+            "gasPrice": "0xdf8475800"
+        }))
+        .unwrap();
+        let mut block = Block { transactions: BlockTransactions::Full(vec![transaction]), ..Default::default() };
+        block.header.hash = hash;
+        block.header.inner.number = number;
+        block
+    }
+
+    #[tokio::test]
+    async fn stores_blocks_once_by_hash() {
+        let inner = test_inner(NodeCacheConfig::default());
+        let full = block(5);
+        inner.record_block(BlockKey::Number(BlockNumberOrTag::Latest), &full, &Provenance::default()).await.unwrap();
+
+        // the number and the tag only point to the block
+        let hash = full.header.hash;
+        assert_eq!(inner.recorder.get::<B256>(&block_hash_key(BlockNumberOrTag::Number(5))).await.unwrap(), Some(hash));
+        assert_eq!(inner.recorder.get::<B256>(&block_hash_key(BlockNumberOrTag::Latest)).await.unwrap(), Some(hash));
+        assert_eq!(inner.get_block(BlockKey::Number(BlockNumberOrTag::Number(5)), true).await.unwrap(), Some(full.clone()));
+
+        // the full block also answers requests for transaction hashes
+        let mut hashes = full.clone();
+        hashes.transactions.convert_to_hashes();
+        assert_eq!(inner.get_block(BlockKey::Hash(hash), false).await.unwrap(), Some(hashes.clone()));
+
+        // and is not replaced by the same block with transaction hashes only
+        inner.record_block(BlockKey::Hash(hash), &hashes, &Provenance::default()).await.unwrap();
+        assert_eq!(inner.get_block(BlockKey::Hash(hash), true).await.unwrap(), Some(full));
+    }
+
+    #[tokio::test]
+    async fn answers_transactions_by_index_from_cached_blocks() {
+        let inner = test_inner(NodeCacheConfig::default());
+        let full = block(5);
+        inner.record_block(BlockKey::Hash(full.header.hash), &full, &Provenance::default()).await.unwrap();
+
+        let transaction = full.transactions.txns().next().cloned();
+        let number = BlockKey::Number(BlockNumberOrTag::Number(5));
+        assert_eq!(inner.get_block_transaction(number, Index(0)).await.unwrap(), transaction);
+        assert_eq!(inner.get_block_transaction(BlockKey::Hash(full.header.hash), Index(1)).await.unwrap(), None);
+        let raw = transaction.map(|transaction| Bytes::from(transaction.inner.encoded_2718()));
+        assert_eq!(inner.get_raw_block_transaction(number, Index(0)).await.unwrap(), raw);
+        // transactions of cached blocks are found by hash
+        let hash = full.transactions.hashes().next().unwrap();
+        assert!(inner.recorder.get::<Transaction>(&transaction_key(hash)).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,82 @@
+use alloy_rpc_types_eth::simulate::SimulatePayload;
+use alloy_rpc_types_eth::state::StateOverride;
+use alloy_rpc_types_eth::{BlockId, StateContext};
+use std::collections::BTreeMap;
+
+/// State overrides are hash maps, sort them to get the same key for the same overrides
+pub fn state_override_key(state_override: Option<&StateOverride>) -> String {
+    let Some(state_override) = state_override else {
+        return format!("{:?}", state_override);
+    };
+    let accounts: BTreeMap<_, _> = state_override
+        .iter()
+        .map(|(address, account)| {
+            let mut account = account.clone();
+            let state = account.state.take().map(|state| state.into_iter().collect::<BTreeMap<_, _>>());
+            let state_diff = account.state_diff.take().map(|state_diff| state_diff.into_iter().collect::<BTreeMap<_, _>>());
+            (address, format!("{:?}{:?}{:?}", account, state, state_diff))
+        })
+        .collect();
+    format!("{:?}", accounts)
+}
+
+pub fn simulate_payload_key(payload: &SimulatePayload) -> String {
+    let mut payload = payload.clone();
+    let blocks: Vec<String> = payload
+        .block_state_calls
+        .iter_mut()
+        .map(|block| {
+            let state_overrides = block.state_overrides.take();
+            format!("{:?}{}", block, state_override_key(state_overrides.as_ref()))
+        })
+        .collect();
+    payload.block_state_calls.clear();
+    format!("{:?}{:?}", blocks, payload)
+}
+
+/// Block a bundle is executed on, bundles without a block run on top of the moving latest block
+pub fn state_context_block(state_context: Option<&StateContext>) -> Option<BlockId> {
+    state_context.and_then(|state_context| state_context.block_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_override(state_override: serde_json::Value) -> StateOverride {
+        serde_json::from_value(state_override).unwrap()
+    }
+
+    #[test]
+    fn keys_do_not_depend_on_the_map_order() {
+        let first = state_override(serde_json::json!({
+            "0x0000000000000000000000000000000000000001": {
+                "balance": "0x1",
+                "stateDiff": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000003",
+                    "0x0000000000000000000000000000000000000000000000000000000000000002": "0x0000000000000000000000000000000000000000000000000000000000000003",
+                },
+            },
+            "0x0000000000000000000000000000000000000002": { "nonce": "0x2" },
+        }));
+        let second = state_override(serde_json::json!({
+            "0x0000000000000000000000000000000000000002": { "nonce": "0x2" },
+            "0x0000000000000000000000000000000000000001": {
+                "stateDiff": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000002": "0x0000000000000000000000000000000000000000000000000000000000000003",
+                    "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000003",
+                },
+                "balance": "0x1",
+            },
+        }));
+        assert_eq!(state_override_key(Some(&first)), state_override_key(Some(&second)));
+    }
+
+    #[test]
+    fn keys_differ_by_overridden_values() {
+        let balance = state_override(serde_json::json!({ "0x0000000000000000000000000000000000000001": { "balance": "0x1" } }));
+        let other_balance = state_override(serde_json::json!({ "0x0000000000000000000000000000000000000001": { "balance": "0x2" } }));
+        assert_ne!(state_override_key(Some(&balance)), state_override_key(Some(&other_balance)));
+        assert_ne!(state_override_key(None), state_override_key(Some(&balance)));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::helper::base::{record_derived, ApiInner};
+use crate::helper::error::eyre_error;
+use alloy_primitives::{keccak256, Address, Bytes, B256, KECCAK256_EMPTY};
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::BlockId;
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+pub const CODE_KEY_PREFIX: &str = "get_code";
+pub const CODE_BY_HASH_KEY_PREFIX: &str = "code_by_hash";
+
+pub fn code_at_key(address: Address, block_number: Option<BlockId>) -> String {
+    format!("{}{}{:?}", CODE_KEY_PREFIX, address, block_number)
+}
+
+pub fn code_key(code_hash: B256) -> String {
+    format!("{}{}", CODE_BY_HASH_KEY_PREFIX, code_hash)
+}
+
+/// Entries of `eth_getCode` only point to the bytecode, older caches still contain the bytecode itself
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CodeEntry {
+    Hash { code_hash: B256 },
+    Code(Bytes),
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Bytecode is stored once by its hash, the entry under the key only holds the hash
+    pub async fn record_code(&self, key: &str, code_hash: B256, code: &Bytes, started: Instant) -> RpcResult<()> {
+        let provenance = self.provenance(started);
+        self.record_with(&code_key(code_hash), code, &provenance).await?;
+        self.record_with(key, &CodeEntry::Hash { code_hash }, &provenance).await
+    }
+
+    pub async fn cached_code(&self, key: &str) -> RpcResult<Option<Bytes>> {
+        match self.recorder.get(key).await.map_err(eyre_error)? {
+            Some(CodeEntry::Hash { code_hash }) => self.code_by_hash(code_hash).await,
+            Some(CodeEntry::Code(code)) => Ok(Some(code)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn code_by_hash(&self, code_hash: B256) -> RpcResult<Option<Bytes>> {
+        if code_hash == KECCAK256_EMPTY {
+            return Ok(Some(Bytes::new()));
+        }
+        self.recorder.get(&code_key(code_hash)).await.map_err(eyre_error)
+    }
+}
+
+/// Move the bytecode of all `eth_getCode` entries to one entry per code hash, returns the number of rewritten entries
+pub async fn dedup_code<R: Recorder + Sync>(recorder: &R) -> eyre::Result<usize> {
+    let mut rewritten = 0;
+    for key in recorder.keys().await? {
+        if !key.starts_with(CODE_KEY_PREFIX) {
+            continue;
+        }
+        let Some(CodeEntry::Code(code)) = recorder.peek(&key).await? else {
+            continue;
+        };
+        let code_hash = keccak256(&code);
+        // both entries keep the provenance of the rewritten entry
+        record_derived(recorder, &code_key(code_hash), &code, &key).await?;
+        record_derived(recorder, &key, &CodeEntry::Hash { code_hash }, &key).await?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NodeCacheConfig;
+    use crate::helper::base::tests::test_inner;
+
+    #[tokio::test]
+    async fn stores_bytecode_once_by_hash() {
+        let inner = test_inner(NodeCacheConfig::default());
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let code_hash = keccak256(&code);
+        let first = code_at_key(Address::repeat_byte(1), None);
+        let second = code_at_key(Address::repeat_byte(2), None);
+        inner.record_code(&first, code_hash, &code, Instant::now()).await.unwrap();
+        inner.record_code(&second, code_hash, &code, Instant::now()).await.unwrap();
+
+        assert_eq!(inner.recorder.keys().await.unwrap().len(), 3);
+        assert_eq!(inner.cached_code(&first).await.unwrap(), Some(code.clone()));
+        assert_eq!(inner.cached_code(&second).await.unwrap(), Some(code));
+        assert_eq!(inner.code_by_hash(KECCAK256_EMPTY).await.unwrap(), Some(Bytes::new()));
+    }
+
+    #[tokio::test]
+    async fn dedups_bytecode_of_older_entries() {
+        let inner = test_inner(NodeCacheConfig::default());
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let key = code_at_key(Address::repeat_byte(1), None);
+        inner.recorder.record(&key, &code).await.unwrap();
+        // older entries are still answered
+        assert_eq!(inner.cached_code(&key).await.unwrap(), Some(code.clone()));
+
+        assert_eq!(dedup_code(inner.recorder.as_ref()).await.unwrap(), 1);
+        assert_eq!(dedup_code(inner.recorder.as_ref()).await.unwrap(), 0);
+        assert_eq!(inner.recorder.get::<Bytes>(&code_key(keccak256(&code))).await.unwrap(), Some(code.clone()));
+        assert_eq!(inner.cached_code(&key).await.unwrap(), Some(code));
+    }
+}
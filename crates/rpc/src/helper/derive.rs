@@ -0,0 +1,96 @@
+use crate::helper::base::ApiInner;
+use crate::helper::error::eyre_error;
+use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{Account, BlockId, EIP1186AccountProofResponse};
+use alloy_serde::JsonStorageKey;
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use std::time::Instant;
+
+pub const GET_ACCOUNT_KEY_PREFIX: &str = "get_account";
+pub const GET_PROOF_KEY_PREFIX: &str = "get_proof";
+pub const PROOF_INDEX_KEY_PREFIX: &str = "proof_index";
+
+/// State requests without a block are answered for the latest block
+fn state_block(block: Option<BlockId>) -> BlockId {
+    block.unwrap_or(BlockId::latest())
+}
+
+pub fn account_key(address: Address, block: BlockId) -> String {
+    format!("{}{}{}", GET_ACCOUNT_KEY_PREFIX, address, block)
+}
+
+pub fn proof_key(address: Address, keys: &[JsonStorageKey], block: Option<BlockId>) -> String {
+    format!("{}{}{:?}{}", GET_PROOF_KEY_PREFIX, address, keys, state_block(block))
+}
+
+/// Keys of all recorded proofs of the account at the block
+fn proof_index_key(address: Address, block: Option<BlockId>) -> String {
+    format!("{}{}{}", PROOF_INDEX_KEY_PREFIX, address, state_block(block))
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Make the proof available to answer account and storage requests of the same block
+    pub async fn index_proof(&self, address: Address, keys: &[JsonStorageKey], block: Option<BlockId>, started: Instant) -> RpcResult<()> {
+        let provenance = self.provenance(started);
+        self.add_to_index(&proof_index_key(address, block), &proof_key(address, keys, block), Some(&provenance)).await
+    }
+
+    async fn cached_proofs(&self, address: Address, block: Option<BlockId>) -> RpcResult<Vec<EIP1186AccountProofResponse>> {
+        let proof_keys: Vec<String> = self.recorder.get(&proof_index_key(address, block)).await.map_err(eyre_error)?.unwrap_or_default();
+        let mut proofs = vec![];
+        for key in proof_keys.iter() {
+            if let Some(proof) = self.recorder.get(key).await.map_err(eyre_error)? {
+                proofs.push(proof);
+            }
+        }
+        Ok(proofs)
+    }
+
+    /// Account from a cached `eth_getAccount` or `eth_getProof` response of the same block
+    pub async fn derive_account(&self, address: Address, block: Option<BlockId>) -> RpcResult<Option<Account>> {
+        if let Some(account) = self.recorder.get(&account_key(address, state_block(block))).await.map_err(eyre_error)? {
+            return Ok(Some(account));
+        }
+        Ok(self.cached_proofs(address, block).await?.into_iter().next().map(|proof| Account {
+            balance: proof.balance,
+            nonce: proof.nonce,
+            code_hash: proof.code_hash,
+            storage_root: proof.storage_hash,
+        }))
+    }
+
+    pub async fn derive_balance(&self, address: Address, block: Option<BlockId>) -> RpcResult<Option<U256>> {
+        Ok(self.derive_account(address, block).await?.map(|account| account.balance))
+    }
+
+    pub async fn derive_nonce(&self, address: Address, block: Option<BlockId>) -> RpcResult<Option<U256>> {
+        Ok(self.derive_account(address, block).await?.map(|account| U256::from(account.nonce)))
+    }
+
+    /// The account only contains the code hash, the bytecode is found if any other account has the same code
+    pub async fn derive_code(&self, address: Address, block: Option<BlockId>) -> RpcResult<Option<Bytes>> {
+        match self.derive_account(address, block).await? {
+            Some(account) => self.code_by_hash(account.code_hash).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Storage value from a cached `eth_getProof` response of the same block
+    pub async fn derive_storage(&self, address: Address, slot: B256, block: Option<BlockId>) -> RpcResult<Option<B256>> {
+        for proof in self.cached_proofs(address, block).await? {
+            if let Some(storage_proof) = proof.storage_proof.iter().find(|storage_proof| storage_proof.key.as_b256() == slot) {
+                return Ok(Some(B256::from(storage_proof.value)));
+            }
+        }
+        Ok(None)
+    }
+}
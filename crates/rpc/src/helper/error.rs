@@ -1,10 +1,40 @@
-use jsonrpsee::types::error::INTERNAL_ERROR_CODE;
+use jsonrpsee::types::error::{INTERNAL_ERROR_CODE, METHOD_NOT_FOUND_CODE};
 use jsonrpsee::types::{ErrorObject, ErrorObjectOwned};
 
 pub fn alloy_error(err: alloy_transport::TransportError) -> ErrorObjectOwned {
     ErrorObject::owned(INTERNAL_ERROR_CODE, format!("Provider error: {:?}", err), None::<bool>)
 }
 
+/// The provider does not know the method
+pub fn is_method_not_found(err: &alloy_transport::TransportError) -> bool {
+    err.as_error_resp().is_some_and(|payload| payload.code == i64::from(METHOD_NOT_FOUND_CODE))
+}
+
 pub fn eyre_error(err: eyre::Report) -> ErrorObjectOwned {
     ErrorObject::owned(INTERNAL_ERROR_CODE, format!("Provider error: {:?}", err), None::<bool>)
 }
+
+pub fn not_recorded_error(method: &str) -> ErrorObjectOwned {
+    ErrorObject::owned(INTERNAL_ERROR_CODE, format!("Request for {} not recorded and no provider available", method), None::<bool>)
+}
+
+pub fn fork_block_error(number: u64, fork_block: u64) -> ErrorObjectOwned {
+    ErrorObject::owned(INTERNAL_ERROR_CODE, format!("Block {} is after the fork block {}", number, fork_block), None::<bool>)
+}
+
+pub fn submission_error(method: &str, reason: &str) -> ErrorObjectOwned {
+    ErrorObject::owned(INTERNAL_ERROR_CODE, format!("Refusing {}: {}", method, reason), None::<bool>)
+}
+
+pub fn unsupported_error(method: &str) -> ErrorObjectOwned {
+    ErrorObject::owned(METHOD_NOT_FOUND_CODE, format!("Method {} is not supported by node cache", method), None::<bool>)
+}
+
+/// The reth debug API declares no result for these methods, node cache serves them through `DebugExtApiServer`
+pub fn debug_ext_error(method: &str) -> ErrorObjectOwned {
+    ErrorObject::owned(
+        METHOD_NOT_FOUND_CODE,
+        format!("Method {} is served by DebugExtApiServer, merge its module instead", method),
+        None::<bool>,
+    )
+}
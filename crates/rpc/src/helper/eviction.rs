@@ -0,0 +1,77 @@
+use crate::helper::block::{block_key, transaction_key, BLOCK_HASH_BY_NUMBER_KEY_PREFIX, SENDER_NONCE_KEY_PREFIX};
+use crate::helper::code::{code_key, CODE_KEY_PREFIX};
+use crate::helper::finality::BLOCK_ENTRY_KEY_PREFIX;
+use crate::helper::sequence::{sequence_length_key, SEQUENCE_KEY_PREFIX};
+use alloy_primitives::B256;
+use node_cache_recorder::EntryLink;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct CodeHash {
+    code_hash: B256,
+}
+
+/// Entries which are only valid together with other entries, so evicting one never breaks the other
+pub fn entry_link(key: &str, value: &serde_json::Value) -> Option<EntryLink> {
+    if key.starts_with(BLOCK_ENTRY_KEY_PREFIX) {
+        // invalidates the entries of a block on a reorg
+        return Some(EntryLink::Pinned);
+    }
+    if key.starts_with(BLOCK_HASH_BY_NUMBER_KEY_PREFIX) {
+        let hash = B256::deserialize(value).ok()?;
+        return Some(EntryLink::EvictedWith(block_key(hash)));
+    }
+    if key.starts_with(SENDER_NONCE_KEY_PREFIX) {
+        let hash = B256::deserialize(value).ok()?;
+        return Some(EntryLink::EvictedWith(transaction_key(hash)));
+    }
+    if key.starts_with(CODE_KEY_PREFIX) {
+        // older caches still contain the bytecode itself
+        let CodeHash { code_hash } = CodeHash::deserialize(value).ok()?;
+        return Some(EntryLink::RefersTo(code_key(code_hash)));
+    }
+    if let Some(sequence) = key.strip_prefix(SEQUENCE_KEY_PREFIX) {
+        // a sequence is replayed from its first value up to its length
+        let (method, position) = sequence.rsplit_once(':')?;
+        return (position != "length").then(|| EntryLink::EvictedWith(sequence_length_key(method)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::sequence::sequence_key;
+    use alloy_primitives::b256;
+    use serde_json::json;
+
+    #[test]
+    fn links_pointers_to_their_targets() {
+        let hash = b256!("0000000000000000000000000000000000000000000000000000000000000001");
+        assert_eq!(entry_link("block_hash_by_number0x5", &json!(hash)), Some(EntryLink::EvictedWith(block_key(hash))));
+        assert_eq!(
+            entry_link("get_code0x0000000000000000000000000000000000000001None", &json!({ "code_hash": hash })),
+            Some(EntryLink::RefersTo(code_key(hash)))
+        );
+        assert_eq!(entry_link("get_code0x0000000000000000000000000000000000000001None", &json!("0x6000")), None);
+        assert_eq!(entry_link("code_by_hash0x01", &json!("0x6000")), None);
+        assert_eq!(
+            entry_link("sender_nonce0x0000000000000000000000000000000000000001:7", &json!(hash)),
+            Some(EntryLink::EvictedWith(transaction_key(hash)))
+        );
+    }
+
+    #[test]
+    fn links_sequence_values_to_the_length() {
+        assert_eq!(
+            entry_link(&sequence_key("gas_price", 3), &json!("0x1")),
+            Some(EntryLink::EvictedWith(sequence_length_key("gas_price")))
+        );
+        assert_eq!(entry_link(&sequence_length_key("gas_price"), &json!(4)), None);
+    }
+
+    #[test]
+    fn pins_the_block_index() {
+        assert_eq!(entry_link("block_entry5", &json!(["get_balance"])), Some(EntryLink::Pinned));
+    }
+}
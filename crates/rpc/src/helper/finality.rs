@@ -0,0 +1,183 @@
+use crate::config::Finality;
+use crate::helper::base::ApiInner;
+use crate::helper::block::{block_hash_key, block_key, sender_nonce_key, transaction_key, BlockKey};
+use crate::helper::error::{alloy_error, eyre_error};
+use alloy_consensus::Transaction as _;
+use alloy_primitives::B256;
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{Block, BlockId, BlockNumberOrTag, Transaction};
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use serde::Serialize;
+use std::time::Instant;
+use tracing::warn;
+
+pub const BLOCK_ENTRY_KEY_PREFIX: &str = "block_entry";
+
+/// Keys of the entries recorded for a block, to invalidate them with the block on a reorg
+fn block_entry_key(number: u64) -> String {
+    format!("{}{}", BLOCK_ENTRY_KEY_PREFIX, number)
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Responses for the block can be recorded, because it can no longer be reorged
+    pub async fn is_final(&self, block: Option<BlockId>) -> RpcResult<bool> {
+        if self.config.finality.is_none() {
+            return Ok(true);
+        }
+        match block {
+            Some(BlockId::Hash(hash)) => match self.lookup_block(BlockKey::Hash(hash.block_hash), false).await? {
+                Some(block) => self.is_final_number(block.header.number).await,
+                None => Ok(false),
+            },
+            Some(BlockId::Number(BlockNumberOrTag::Number(number))) => self.is_final_number(number).await,
+            Some(BlockId::Number(BlockNumberOrTag::Earliest)) => Ok(true),
+            // tags and the latest block move with the chain
+            _ => Ok(false),
+        }
+    }
+
+    /// Record the response if the block can no longer be reorged
+    pub async fn record_for_block<V: Serialize + Send + Sync>(
+        &self,
+        block: Option<BlockId>,
+        key: &str,
+        value: &V,
+        started: Instant,
+    ) -> RpcResult<()> {
+        if !self.is_final(block).await? {
+            return Ok(());
+        }
+        self.record(key, value, started).await?;
+        self.index_block_entry(block, key).await
+    }
+
+    /// Record the response for a transaction if its block can no longer be reorged, pending transactions are never recorded
+    pub async fn record_for_transaction<V: Serialize + Send + Sync>(
+        &self,
+        hash: B256,
+        key: &str,
+        value: &V,
+        started: Instant,
+    ) -> RpcResult<()> {
+        match self.transaction_block(hash).await? {
+            Some(number) => self.record_for_block(Some(BlockId::number(number)), key, value, started).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Block of a mined transaction, from the cached transaction or the provider
+    pub async fn transaction_block(&self, hash: B256) -> RpcResult<Option<u64>> {
+        if let Some(transaction) = self.recorder.peek::<Transaction>(&transaction_key(hash)).await.map_err(eyre_error)? {
+            return Ok(transaction.block_number);
+        }
+        let Some(provider) = self.provider.as_ref() else {
+            return Ok(None);
+        };
+        Ok(provider.get_transaction_by_hash(hash).await.map_err(alloy_error)?.and_then(|transaction| transaction.block_number))
+    }
+
+    /// Remember the entry with the number of its block, blocks requested by hash are looked up
+    pub async fn index_block_entry(&self, block: Option<BlockId>, key: &str) -> RpcResult<()> {
+        let number = match block {
+            Some(BlockId::Number(BlockNumberOrTag::Number(number))) => number,
+            Some(BlockId::Hash(hash)) => match self.lookup_block(BlockKey::Hash(hash.block_hash), false).await? {
+                Some(block) => block.header.number,
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        self.add_to_index(&block_entry_key(number), key, None).await
+    }
+
+    /// Transactions are indexed with the block they were mined in
+    pub async fn index_transaction_entry(&self, transaction: &Transaction, key: &str) -> RpcResult<()> {
+        self.index_block_entry(transaction.block_number.map(BlockId::number), key).await
+    }
+
+    pub async fn is_final_number(&self, number: u64) -> RpcResult<bool> {
+        if self.config.finality.is_none() {
+            return Ok(true);
+        }
+        Ok(self.finalized_head().await?.is_some_and(|finalized| number <= finalized))
+    }
+
+    async fn finalized_head(&self) -> RpcResult<Option<u64>> {
+        if let Some((fetched_at, finalized)) = *self.finalized_head.lock().unwrap() {
+            // the finalized block moves once per epoch, one block time is more than accurate enough
+            if fetched_at.elapsed() < self.config.block_time() {
+                return Ok(Some(finalized));
+            }
+        }
+        let (Some(provider), Some(finality)) = (self.provider.as_ref(), self.config.finality) else {
+            return Ok(None);
+        };
+        let finalized = match finality {
+            Finality::Finalized => {
+                let Some(block) = provider.get_block_by_number(BlockNumberOrTag::Finalized, false.into()).await.map_err(alloy_error)?
+                else {
+                    return Ok(None);
+                };
+                // even a finalized block can be reorged, the cache should know about it
+                self.check_reorg(block.header.number, block.header.hash).await?;
+                block.header.number
+            }
+            Finality::Confirmations(confirmations) => {
+                let finalized = provider.get_block_number().await.map_err(alloy_error)?.saturating_sub(confirmations);
+                // a reorg deeper than the confirmations replaces blocks which were already recorded
+                for number in finalized.saturating_sub(confirmations) + 1..=finalized {
+                    self.recheck_block(provider, number).await?;
+                }
+                finalized
+            }
+        };
+        *self.finalized_head.lock().unwrap() = Some((Instant::now(), finalized));
+        Ok(Some(finalized))
+    }
+
+    /// Compare the block hash from the provider with the cached one and invalidate the cached block on a mismatch
+    pub async fn check_reorg(&self, number: u64, hash: B256) -> RpcResult<()> {
+        let cached_hash = self.recorder.peek::<B256>(&block_hash_key(BlockNumberOrTag::Number(number))).await.map_err(eyre_error)?;
+        match cached_hash {
+            Some(cached_hash) if cached_hash != hash => self.invalidate_block(number, cached_hash).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Compare a cached block with the block the provider has at its number
+    async fn recheck_block(&self, provider: &P, number: u64) -> RpcResult<()> {
+        let number = BlockNumberOrTag::Number(number);
+        if self.recorder.peek::<B256>(&block_hash_key(number)).await.map_err(eyre_error)?.is_none() {
+            return Ok(());
+        }
+        match provider.get_block_by_number(number, false.into()).await.map_err(alloy_error)? {
+            Some(block) => self.check_reorg(block.header.number, block.header.hash).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Remove the block, its transactions and all entries indexed for the block number
+    async fn invalidate_block(&self, number: u64, hash: B256) -> RpcResult<()> {
+        let _guard = self.config.index_lock.lock().await;
+        let index_key = block_entry_key(number);
+        let mut keys = vec![block_hash_key(BlockNumberOrTag::Number(number)), block_key(hash)];
+        if let Some(block) = self.recorder.peek::<Block>(&block_key(hash)).await.map_err(eyre_error)? {
+            keys.extend(block.transactions.hashes().map(transaction_key));
+            keys.extend(block.transactions.txns().map(|transaction| sender_nonce_key(transaction.from, transaction.inner.nonce())));
+        }
+        keys.extend(self.recorder.peek::<Vec<String>>(&index_key).await.map_err(eyre_error)?.unwrap_or_default());
+        keys.push(index_key);
+        for key in keys.iter() {
+            self.recorder.remove(key).await.map_err(eyre_error)?;
+        }
+        warn!("Reorg detected at block {}, invalidated {} entries of block {}", number, keys.len(), hash);
+        Ok(())
+    }
+}
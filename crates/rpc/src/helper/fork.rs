@@ -0,0 +1,140 @@
+use crate::helper::base::ApiInner;
+use crate::helper::block::BlockKey;
+use crate::helper::error::fork_block_error;
+use alloy_primitives::B256;
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{BlockId, BlockNumberOrTag, StateContext, Transaction};
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder,
+{
+    /// Blocks after the fork block do not exist
+    pub fn check_fork_block(&self, number: u64) -> RpcResult<()> {
+        match self.config.fork_block {
+            Some(fork_block) if number > fork_block => Err(fork_block_error(number, fork_block)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Tags are rewritten to the fork block, so they are cached like the fork block itself
+    pub fn pin_block_number(&self, number: BlockNumberOrTag) -> RpcResult<BlockNumberOrTag> {
+        let Some(fork_block) = self.config.fork_block else {
+            return Ok(number);
+        };
+        match number {
+            BlockNumberOrTag::Number(number) => {
+                self.check_fork_block(number)?;
+                Ok(BlockNumberOrTag::Number(number))
+            }
+            BlockNumberOrTag::Earliest => Ok(BlockNumberOrTag::Earliest),
+            _ => Ok(BlockNumberOrTag::Number(fork_block)),
+        }
+    }
+
+    pub fn pin_block_key(&self, block: BlockKey) -> RpcResult<BlockKey> {
+        match block {
+            BlockKey::Number(number) => Ok(BlockKey::Number(self.pin_block_number(number)?)),
+            BlockKey::Hash(hash) => Ok(BlockKey::Hash(hash)),
+        }
+    }
+
+    /// Transactions mined after the fork block do not exist, pending transactions are kept
+    pub fn check_fork_transaction(&self, transaction: Option<&Transaction>) -> RpcResult<()> {
+        match transaction.and_then(|transaction| transaction.block_number) {
+            Some(number) => self.check_fork_block(number),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// A missing block defaults to the latest block, which is the fork block. A block hash must not be after it.
+    pub async fn pin_block_id(&self, block: Option<BlockId>) -> RpcResult<Option<BlockId>> {
+        let Some(fork_block) = self.config.fork_block else {
+            return Ok(block);
+        };
+        match block {
+            Some(BlockId::Number(number)) => Ok(Some(BlockId::Number(self.pin_block_number(number)?))),
+            Some(BlockId::Hash(hash)) => {
+                // the block store rejects blocks after the fork block
+                self.lookup_block(BlockKey::Hash(hash.block_hash), false).await?;
+                Ok(Some(BlockId::Hash(hash)))
+            }
+            None => Ok(Some(BlockId::number(fork_block))),
+        }
+    }
+
+    pub async fn pin_state_context(&self, state_context: Option<StateContext>) -> RpcResult<Option<StateContext>> {
+        if self.config.fork_block.is_none() {
+            return Ok(state_context);
+        }
+        let mut state_context = state_context.unwrap_or_default();
+        state_context.block_number = self.pin_block_id(state_context.block_number).await?;
+        Ok(Some(state_context))
+    }
+
+    /// Responses keyed by a transaction hash do not exist if the transaction was mined after the fork block
+    pub async fn check_fork_transaction_hash(&self, hash: B256) -> RpcResult<()> {
+        if self.config.fork_block.is_none() {
+            return Ok(());
+        }
+        match self.transaction_block(hash).await? {
+            Some(number) => self.check_fork_block(number),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NodeCacheConfig;
+    use crate::helper::base::tests::test_inner;
+    use crate::helper::block::block_key;
+    use alloy_rpc_types_eth::{Block, Header};
+
+    #[test]
+    fn pins_tags_to_the_fork_block() {
+        let inner = test_inner(NodeCacheConfig { fork_block: Some(100), ..Default::default() });
+        assert_eq!(inner.pin_block_number(BlockNumberOrTag::Latest).unwrap(), BlockNumberOrTag::Number(100));
+        assert_eq!(inner.pin_block_number(BlockNumberOrTag::Finalized).unwrap(), BlockNumberOrTag::Number(100));
+        assert_eq!(inner.pin_block_number(BlockNumberOrTag::Earliest).unwrap(), BlockNumberOrTag::Earliest);
+        assert_eq!(inner.pin_block_number(BlockNumberOrTag::Number(100)).unwrap(), BlockNumberOrTag::Number(100));
+        assert!(inner.pin_block_number(BlockNumberOrTag::Number(101)).is_err());
+
+        let inner = test_inner(NodeCacheConfig::default());
+        assert_eq!(inner.pin_block_number(BlockNumberOrTag::Latest).unwrap(), BlockNumberOrTag::Latest);
+    }
+
+    #[tokio::test]
+    async fn rejects_block_hashes_after_the_fork_block() {
+        let inner = test_inner(NodeCacheConfig { fork_block: Some(100), ..Default::default() });
+        assert_eq!(inner.pin_block_id(None).await.unwrap(), Some(BlockId::number(100)));
+        for number in [100, 101] {
+            let block = Block {
+                header: Header {
+                    hash: B256::repeat_byte(number as u8),
+                    inner: alloy_consensus::Header { number, ..Default::default() },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            inner.recorder.record(&block_key(block.header.hash), &block).await.unwrap();
+        }
+        let before = BlockId::hash(B256::repeat_byte(100));
+        assert_eq!(inner.pin_block_id(Some(before)).await.unwrap(), Some(before));
+        assert!(inner.pin_block_id(Some(BlockId::hash(B256::repeat_byte(101)))).await.is_err());
+    }
+}
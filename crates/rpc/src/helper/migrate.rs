@@ -0,0 +1,156 @@
+use crate::helper::base::{record_derived, KEY_SCHEMA_VERSION};
+use crate::helper::block::{block_hash_key, block_key, transaction_key};
+use crate::helper::code::dedup_code;
+use alloy_provider::network::TransactionResponse;
+use alloy_rpc_types_eth::{Block, BlockNumberOrTag};
+use eyre::bail;
+use node_cache_recorder::Recorder;
+
+/// Blocks of unversioned databases were stored per number, tag and transaction detail
+const LEGACY_BLOCK_KEY_PREFIX: &str = "block_by_number";
+
+/// Refuse to start with a database recorded in another key schema, it would not produce a single hit
+pub async fn check_schema<R: Recorder + Sync>(recorder: &R) -> eyre::Result<()> {
+    match recorder.schema_version().await? {
+        Some(version) if version == KEY_SCHEMA_VERSION => Ok(()),
+        Some(version) if version > KEY_SCHEMA_VERSION => {
+            bail!("Database has key schema version {}, this node cache only supports up to {}", version, KEY_SCHEMA_VERSION)
+        }
+        Some(version) => {
+            bail!("Database has key schema version {}, expected {}, upgrade it with `node-cache migrate`", version, KEY_SCHEMA_VERSION)
+        }
+        // a new database starts with the current schema
+        None if recorder.keys().await?.is_empty() => recorder.set_schema_version(KEY_SCHEMA_VERSION).await,
+        None => bail!("Database has no key schema version, upgrade it with `node-cache migrate`"),
+    }
+}
+
+/// Upgrade the database to the current key schema, returns the number of rewritten entries
+pub async fn migrate<R: Recorder + Sync>(recorder: &R) -> eyre::Result<usize> {
+    let from = recorder.schema_version().await?.unwrap_or(0);
+    if from > KEY_SCHEMA_VERSION {
+        bail!("Database has key schema version {}, this node cache only supports up to {}", from, KEY_SCHEMA_VERSION);
+    }
+    let mut rewritten = 0;
+    for version in from..KEY_SCHEMA_VERSION {
+        rewritten += match version {
+            0 => migrate_unversioned(recorder).await?,
+            _ => bail!("No migration from key schema version {}", version),
+        };
+        recorder.set_schema_version(version + 1).await?;
+    }
+    Ok(rewritten)
+}
+
+/// Unversioned databases store blocks per requested number and the bytecode in every `eth_getCode` entry
+async fn migrate_unversioned<R: Recorder + Sync>(recorder: &R) -> eyre::Result<usize> {
+    let mut rewritten = 0;
+    for key in recorder.keys().await? {
+        if !key.starts_with(LEGACY_BLOCK_KEY_PREFIX) {
+            continue;
+        }
+        if let Some(block) = recorder.peek::<Block>(&key).await? {
+            migrate_block(recorder, &key, &block).await?;
+        }
+        recorder.remove(&key).await?;
+        rewritten += 1;
+    }
+    Ok(rewritten + dedup_code(recorder).await?)
+}
+
+/// Store the block once by hash like the block store does, legacy entries of tags do not move the tags anymore.
+/// All entries keep the provenance of the legacy entry.
+async fn migrate_block<R: Recorder + Sync>(recorder: &R, legacy_key: &str, block: &Block) -> eyre::Result<()> {
+    let hash = block.header.hash;
+    let cached_full = match recorder.peek::<Block>(&block_key(hash)).await? {
+        Some(cached) => cached.transactions.is_full(),
+        None => false,
+    };
+    if block.transactions.is_full() || !cached_full {
+        record_derived(recorder, &block_key(hash), block, legacy_key).await?;
+    }
+    for transaction in block.transactions.txns() {
+        record_derived(recorder, &transaction_key(transaction.tx_hash()), transaction, legacy_key).await?;
+    }
+    let number_key = block_hash_key(BlockNumberOrTag::Number(block.header.number));
+    if recorder.peek::<serde_json::Value>(&number_key).await?.is_none() {
+        record_derived(recorder, &number_key, &hash, legacy_key).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::code::{code_at_key, code_key};
+    use alloy_primitives::{keccak256, Address, Bytes, B256};
+    use alloy_rpc_types_eth::{BlockTransactions, Header};
+    use node_cache_recorder::{MemoryRecorder, Provenance};
+
+    fn block(number: u64) -> Block {
+        Block {
+            header: Header {
+                hash: B256::repeat_byte(number as u8),
+                inner: alloy_consensus::Header { number, ..Default::default() },
+                ..Default::default()
+            },
+            transactions: BlockTransactions::Hashes(vec![B256::repeat_byte(0xee)]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn new_database_gets_the_current_schema() {
+        let recorder = MemoryRecorder::new();
+        check_schema(&recorder).await.unwrap();
+        assert_eq!(recorder.schema_version().await.unwrap(), Some(KEY_SCHEMA_VERSION));
+        check_schema(&recorder).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_other_schemas() {
+        let recorder = MemoryRecorder::new();
+        recorder.set_schema_version(KEY_SCHEMA_VERSION + 1).await.unwrap();
+        assert!(check_schema(&recorder).await.is_err());
+
+        let recorder = MemoryRecorder::new();
+        recorder.record("chain_id", &1).await.unwrap();
+        assert!(check_schema(&recorder).await.is_err());
+        assert_eq!(recorder.schema_version().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn migrates_unversioned_database() {
+        let recorder = MemoryRecorder::new();
+        let provenance = Provenance { recorded_at: 1, ..Provenance::default() };
+        let legacy = block(5);
+        let legacy_key = format!("{}{}{}", LEGACY_BLOCK_KEY_PREFIX, BlockNumberOrTag::Number(5), false);
+        recorder.record_with_provenance(&legacy_key, &legacy, &provenance).await.unwrap();
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let code_key_at = code_at_key(Address::ZERO, None);
+        recorder.record(&code_key_at, &code).await.unwrap();
+
+        assert_eq!(migrate(&recorder).await.unwrap(), 2);
+        check_schema(&recorder).await.unwrap();
+
+        assert_eq!(recorder.get::<Block>(&legacy_key).await.unwrap(), None);
+        assert_eq!(recorder.get::<Block>(&block_key(legacy.header.hash)).await.unwrap(), Some(legacy.clone()));
+        assert_eq!(recorder.provenance(&block_key(legacy.header.hash)).await.unwrap(), Some(provenance));
+        let number_key = block_hash_key(BlockNumberOrTag::Number(5));
+        assert_eq!(recorder.get::<B256>(&number_key).await.unwrap(), Some(legacy.header.hash));
+
+        let code_hash = keccak256(&code);
+        assert_eq!(recorder.get::<Bytes>(&code_key(code_hash)).await.unwrap(), Some(code));
+        assert_eq!(recorder.get::<serde_json::Value>(&code_key_at).await.unwrap(), Some(serde_json::json!({ "code_hash": code_hash })));
+    }
+
+    #[tokio::test]
+    async fn migrating_twice_rewrites_nothing() {
+        let recorder = MemoryRecorder::new();
+        recorder.record(&format!("{}{}", LEGACY_BLOCK_KEY_PREFIX, BlockNumberOrTag::Latest), &block(7)).await.unwrap();
+        assert_eq!(migrate(&recorder).await.unwrap(), 1);
+        assert_eq!(migrate(&recorder).await.unwrap(), 0);
+        // tags of legacy entries no longer point to the block
+        assert_eq!(recorder.get::<B256>(&block_hash_key(BlockNumberOrTag::Latest)).await.unwrap(), None);
+    }
+}
@@ -1,2 +1,15 @@
 pub mod base;
+pub mod block;
+pub mod call;
+pub mod code;
+pub mod derive;
 pub mod error;
+pub mod eviction;
+pub mod finality;
+pub mod fork;
+pub mod migrate;
+pub mod sequence;
+pub mod state;
+pub mod trace;
+pub mod ttl;
+pub mod verify;
@@ -0,0 +1,94 @@
+use crate::helper::base::ApiInner;
+use crate::helper::error::eyre_error;
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Instant;
+
+pub const SEQUENCE_KEY_PREFIX: &str = "sequence";
+
+/// All values of the method and the length of its sequence
+pub fn sequence_key_prefix(method: &str) -> String {
+    format!("{}{}:", SEQUENCE_KEY_PREFIX, method)
+}
+
+pub fn sequence_key(method: &str, position: usize) -> String {
+    format!("{}{}", sequence_key_prefix(method), position)
+}
+
+pub fn sequence_length_key(method: &str) -> String {
+    format!("{}length", sequence_key_prefix(method))
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Position of the current call of the method, counted since start
+    fn next_sequence_position(&self, method: &'static str) -> usize {
+        let mut positions = self.sequence_positions.lock().unwrap();
+        let position = positions.entry(method).or_insert(0);
+        *position += 1;
+        *position - 1
+    }
+
+    /// Store the response as the N-th value of the method, a new recording replaces the previous sequence
+    pub async fn record_sequence<V: Serialize + Send + Sync>(&self, method: &'static str, value: &V, started: Instant) -> RpcResult<()> {
+        if !self.config.record_sequences {
+            return Ok(());
+        }
+        let position = self.next_sequence_position(method);
+        let provenance = self.provenance(started);
+        self.record_with(&sequence_key(method, position), value, &provenance).await?;
+        // values after the new length are left over from a previous recording and are overwritten later
+        self.record_with(&sequence_length_key(method), &(position + 1), &provenance).await
+    }
+
+    /// The N-th call returns the N-th recorded value and all calls after the end of the sequence return the last value
+    pub async fn replay_sequence<V: DeserializeOwned>(&self, method: &'static str) -> RpcResult<Option<V>> {
+        let position = self.next_sequence_position(method);
+        let Some(length) = self.recorder.get::<usize>(&sequence_length_key(method)).await.map_err(eyre_error)? else {
+            return Ok(None);
+        };
+        self.recorder.get(&sequence_key(method, position.min(length.saturating_sub(1)))).await.map_err(eyre_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NodeCacheConfig;
+    use crate::helper::base::build_inner;
+    use crate::helper::base::tests::{test_inner, TestApiInner};
+
+    #[tokio::test]
+    async fn replays_values_in_call_order() {
+        let recording = test_inner(NodeCacheConfig { record_sequences: true, ..Default::default() });
+        for value in [7u64, 8, 9] {
+            recording.record_sequence("block_number", &value, Instant::now()).await.unwrap();
+        }
+        assert_eq!(recording.recorder.get::<usize>(&sequence_length_key("block_number")).await.unwrap(), Some(3));
+
+        // a new session starts at the beginning of the sequence and repeats the last value
+        let replay: TestApiInner = build_inner(None, recording.recorder.clone(), NodeCacheConfig::default());
+        let mut replayed = vec![];
+        for _ in 0..4 {
+            replayed.push(replay.replay_sequence::<u64>("block_number").await.unwrap());
+        }
+        assert_eq!(replayed, vec![Some(7), Some(8), Some(9), Some(9)]);
+        assert_eq!(replay.replay_sequence::<u64>("gas_price").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn records_nothing_unless_enabled() {
+        let inner = test_inner(NodeCacheConfig::default());
+        inner.record_sequence("block_number", &7u64, Instant::now()).await.unwrap();
+        assert!(inner.recorder.keys().await.unwrap().is_empty());
+    }
+}
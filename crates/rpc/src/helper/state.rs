@@ -0,0 +1,305 @@
+use crate::helper::base::ApiInner;
+use crate::helper::block::BlockKey;
+use crate::helper::error::eyre_error;
+use alloy_primitives::{address, Address, B256};
+use alloy_provider::network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{Block, BlockId, BlockNumberOrTag};
+use alloy_rpc_types_trace::geth::{DiffMode, GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingOptions, TraceResult};
+use alloy_serde::JsonStorageKey;
+use alloy_transport::Transport;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::{Provenance, Recorder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
+
+pub const STORAGE_AT_KEY_PREFIX: &str = "storage_at";
+pub const STATE_VALUES_BALANCE_KEY_PREFIX: &str = "state_values_balance";
+pub const STATE_VALUES_NONCE_KEY_PREFIX: &str = "state_values_nonce";
+pub const STATE_VALUES_CODE_KEY_PREFIX: &str = "state_values_code";
+pub const STATE_VALUES_STORAGE_KEY_PREFIX: &str = "state_values_storage";
+pub const STATE_CHANGES_KEY_PREFIX: &str = "state_changes";
+
+/// Maximum distance between the requested and the recorded block, about one hour of blocks
+const MAX_DERIVE_BLOCKS: u64 = 300;
+
+/// Contracts written by the protocol outside of transactions: beacon roots, history storage, withdrawal and consolidation requests
+const SYSTEM_CONTRACTS: [Address; 4] = [
+    address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02"),
+    address!("0000F90827F1C53a10cb7A02335B175320002935"),
+    address!("00000961Ef480Eb55e80D19ad83579A64c007002"),
+    address!("0000BBdDc7CE488642fb579F8B00f3a590007251"),
+];
+
+pub fn storage_at_key(address: Address, index: JsonStorageKey, block_number: Option<BlockId>) -> String {
+    format!("{}{}{}{:?}", STORAGE_AT_KEY_PREFIX, address, index, block_number)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StateField {
+    Balance,
+    Nonce,
+    Code,
+    Storage(B256),
+}
+
+/// Values of an account field recorded at any block
+fn state_values_key_prefix(field: StateField, address: Address) -> String {
+    match field {
+        StateField::Balance => format!("{}{}", STATE_VALUES_BALANCE_KEY_PREFIX, address),
+        StateField::Nonce => format!("{}{}", STATE_VALUES_NONCE_KEY_PREFIX, address),
+        StateField::Code => format!("{}{}", STATE_VALUES_CODE_KEY_PREFIX, address),
+        StateField::Storage(slot) => format!("{}{}{}", STATE_VALUES_STORAGE_KEY_PREFIX, address, slot),
+    }
+}
+
+/// Value of an account field after the block, one entry per block so recording never rewrites other values
+fn state_value_key(field: StateField, address: Address, number: u64) -> String {
+    format!("{}:{}", state_values_key_prefix(field, address), number)
+}
+
+fn state_changes_key(number: u64) -> String {
+    format!("{}{}", STATE_CHANGES_KEY_PREFIX, number)
+}
+
+/// Only the prestate tracer in diff mode shows every account field a transaction changed
+fn is_state_diff_tracer(opts: Option<&GethDebugTracingOptions>) -> bool {
+    let Some(opts) = opts else {
+        return false;
+    };
+    matches!(opts.tracer, Some(GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer)))
+        && opts.tracer_config.clone().into_pre_state_config().is_ok_and(|config| config.diff_mode.unwrap_or_default())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AccountChanges {
+    balance: bool,
+    nonce: bool,
+    code: bool,
+    destroyed: bool,
+    storage: BTreeSet<B256>,
+}
+
+/// Account fields changed by a block, collected from the prestate tracer in diff mode
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BlockStateChanges {
+    accounts: BTreeMap<Address, AccountChanges>,
+    /// Uncle rewards change balances of accounts which are not part of the block
+    unknown_balances: bool,
+}
+
+impl BlockStateChanges {
+    fn add(&mut self, diff: &DiffMode) {
+        for (address, state) in diff.pre.iter().chain(diff.post.iter()) {
+            let changes = self.accounts.entry(*address).or_default();
+            changes.balance |= state.balance.is_some();
+            changes.nonce |= state.nonce.is_some();
+            changes.code |= state.code.is_some();
+            changes.storage.extend(state.storage.keys().copied());
+        }
+        // self destructed accounts lose their storage, including slots the trace does not show
+        for address in diff.pre.keys().filter(|address| !diff.post.contains_key(*address)) {
+            self.accounts.entry(*address).or_default().destroyed = true;
+        }
+    }
+
+    /// Block rewards and withdrawals change balances outside of transactions
+    fn add_block(&mut self, block: &Block) {
+        self.accounts.entry(block.header.beneficiary).or_default().balance = true;
+        if let Some(withdrawals) = block.withdrawals.as_ref() {
+            for withdrawal in withdrawals.iter() {
+                self.accounts.entry(withdrawal.address).or_default().balance = true;
+            }
+        }
+        self.unknown_balances = !block.uncles.is_empty();
+    }
+
+    fn touches(&self, address: Address, field: StateField) -> bool {
+        if SYSTEM_CONTRACTS.contains(&address) || (matches!(field, StateField::Balance) && self.unknown_balances) {
+            return true;
+        }
+        match self.accounts.get(&address) {
+            Some(changes) => {
+                changes.destroyed
+                    || match field {
+                        StateField::Balance => changes.balance,
+                        StateField::Nonce => changes.nonce,
+                        StateField::Code => changes.code,
+                        StateField::Storage(slot) => changes.storage.contains(&slot),
+                    }
+            }
+            None => false,
+        }
+    }
+}
+
+fn block_number(block: Option<BlockId>) -> Option<u64> {
+    match block {
+        Some(BlockId::Number(BlockNumberOrTag::Number(number))) => Some(number),
+        _ => None,
+    }
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Remember the value of the account field at the block, to derive it for other blocks
+    pub async fn record_state<V: Serialize + Send + Sync>(
+        &self,
+        field: StateField,
+        address: Address,
+        block: Option<BlockId>,
+        value: &V,
+        started: Instant,
+    ) -> RpcResult<()> {
+        let Some(number) = block_number(block).filter(|_| self.config.derive_state) else {
+            return Ok(());
+        };
+        if !self.is_final_number(number).await? {
+            return Ok(());
+        }
+        let key = state_value_key(field, address, number);
+        self.record(&key, value, started).await?;
+        self.index_block_entry(block, &key).await
+    }
+
+    /// The value recorded at a nearby block is still valid if no block in between changed the account field.
+    /// Only blocks whose state diffs were recorded from `debug_traceBlockByNumber` are crossed.
+    pub async fn derive_state<V: DeserializeOwned>(
+        &self,
+        field: StateField,
+        address: Address,
+        block: Option<BlockId>,
+    ) -> RpcResult<Option<V>> {
+        let Some(number) = block_number(block).filter(|_| self.config.derive_state) else {
+            return Ok(None);
+        };
+        // the state after an earlier block, if no block up to the requested one changed the field
+        for recorded in (number.saturating_sub(MAX_DERIVE_BLOCKS)..=number).rev() {
+            if let Some(value) = self.recorder.peek(&state_value_key(field, address, recorded)).await.map_err(eyre_error)? {
+                return Ok(Some(value));
+            }
+            if !self.is_unchanged(recorded, address, field).await? {
+                break;
+            }
+        }
+        // the state after a later block, if no block after the requested one up to it changed the field
+        for recorded in number + 1..=number + MAX_DERIVE_BLOCKS {
+            if self.check_fork_block(recorded).is_err() || !self.is_unchanged(recorded, address, field).await? {
+                break;
+            }
+            if let Some(value) = self.recorder.peek(&state_value_key(field, address, recorded)).await.map_err(eyre_error)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Blocks without recorded state diffs may have changed anything
+    async fn is_unchanged(&self, number: u64, address: Address, field: StateField) -> RpcResult<bool> {
+        let changes: Option<BlockStateChanges> = self.recorder.peek(&state_changes_key(number)).await.map_err(eyre_error)?;
+        Ok(changes.is_some_and(|changes| !changes.touches(address, field)))
+    }
+
+    /// Remember the account fields changed by the block, if the traces are state diffs of all of its transactions
+    pub async fn record_state_changes(
+        &self,
+        number: u64,
+        opts: Option<&GethDebugTracingOptions>,
+        traces: &[TraceResult],
+        provenance: &Provenance,
+    ) -> RpcResult<()> {
+        if !self.config.derive_state || !is_state_diff_tracer(opts) {
+            return Ok(());
+        }
+        let mut changes = BlockStateChanges::default();
+        for trace in traces.iter() {
+            // a failed trace hides the changes of its transaction
+            let TraceResult::Success { result, .. } = trace else {
+                return Ok(());
+            };
+            let Ok(diff) = serde_json::to_value(result).and_then(serde_json::from_value::<DiffMode>) else {
+                return Ok(());
+            };
+            changes.add(&diff);
+        }
+        let Some(block) = self.lookup_block(BlockKey::Number(BlockNumberOrTag::Number(number)), false).await? else {
+            return Ok(());
+        };
+        changes.add_block(&block);
+        let key = state_changes_key(number);
+        self.record_with(&key, &changes, provenance).await?;
+        self.index_block_entry(Some(BlockId::number(number)), &key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NodeCacheConfig;
+    use crate::helper::base::tests::test_inner;
+    use alloy_primitives::U256;
+
+    const ACCOUNT: Address = address!("0000000000000000000000000000000000000001");
+
+    fn slot(slot: u8) -> B256 {
+        B256::with_last_byte(slot)
+    }
+
+    fn changes(diff: serde_json::Value) -> BlockStateChanges {
+        let mut changes = BlockStateChanges::default();
+        changes.add(&serde_json::from_value(diff).unwrap());
+        changes
+    }
+
+    #[test]
+    fn touches_the_changed_fields_only() {
+        let changes = changes(serde_json::json!({
+            "pre": { ACCOUNT.to_string(): { "balance": "0x1", "storage": { slot(1).to_string(): slot(2) } } },
+            "post": { ACCOUNT.to_string(): { "balance": "0x2" } },
+        }));
+        assert!(changes.touches(ACCOUNT, StateField::Balance));
+        assert!(changes.touches(ACCOUNT, StateField::Storage(slot(1))));
+        assert!(!changes.touches(ACCOUNT, StateField::Nonce));
+        assert!(!changes.touches(ACCOUNT, StateField::Code));
+        assert!(!changes.touches(ACCOUNT, StateField::Storage(slot(2))));
+        assert!(!changes.touches(Address::ZERO, StateField::Balance));
+        // system contracts are written outside of transactions
+        assert!(changes.touches(SYSTEM_CONTRACTS[0], StateField::Storage(slot(1))));
+    }
+
+    #[test]
+    fn destroyed_accounts_lose_all_fields() {
+        let changes = changes(serde_json::json!({ "pre": { ACCOUNT.to_string(): { "balance": "0x1" } }, "post": {} }));
+        assert!(changes.touches(ACCOUNT, StateField::Nonce));
+        assert!(changes.touches(ACCOUNT, StateField::Storage(slot(3))));
+    }
+
+    #[tokio::test]
+    async fn derives_state_across_unchanged_blocks() {
+        let inner = test_inner(NodeCacheConfig { derive_state: true, ..Default::default() });
+        let balance = U256::from(5);
+        inner.recorder.record(&state_value_key(StateField::Balance, ACCOUNT, 10), &balance).await.unwrap();
+        let mut changed = BlockStateChanges::default();
+        changed.accounts.entry(ACCOUNT).or_default().balance = true;
+        inner.recorder.record(&state_changes_key(11), &BlockStateChanges::default()).await.unwrap();
+        inner.recorder.record(&state_changes_key(12), &changed).await.unwrap();
+
+        let derive = |number: u64| inner.derive_state::<U256>(StateField::Balance, ACCOUNT, Some(BlockId::number(number)));
+        assert_eq!(derive(10).await.unwrap(), Some(balance));
+        assert_eq!(derive(11).await.unwrap(), Some(balance));
+        // block 12 changed the balance
+        assert_eq!(derive(12).await.unwrap(), None);
+        // the changes of block 10 are not known
+        assert_eq!(derive(9).await.unwrap(), None);
+        inner.recorder.record(&state_changes_key(10), &BlockStateChanges::default()).await.unwrap();
+        assert_eq!(derive(9).await.unwrap(), Some(balance));
+        // other fields of the account are not recorded
+        assert_eq!(inner.derive_state::<u64>(StateField::Nonce, ACCOUNT, Some(BlockId::number(11))).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::helper::call::state_override_key;
+use alloy_primitives::map::HashSet;
+use alloy_primitives::B256;
+use alloy_rpc_types_trace::geth::{GethDebugTracingCallOptions, GethDebugTracingOptions};
+use alloy_rpc_types_trace::parity::TraceType;
+
+pub const DEBUG_TRACE_TRANSACTION_KEY_PREFIX: &str = "debug_trace_transaction";
+
+/// The timeout does not change the trace and an empty tracer config is the same as none
+pub fn tracing_options_key(opts: Option<&GethDebugTracingOptions>) -> String {
+    let mut opts = opts.cloned().unwrap_or_default();
+    opts.timeout = None;
+    if opts.tracer_config.0.as_object().is_some_and(|config| config.is_empty()) {
+        opts.tracer_config = Default::default();
+    }
+    format!("{:?}", opts)
+}
+
+pub fn trace_transaction_key(tx_hash: B256, opts: Option<&GethDebugTracingOptions>) -> String {
+    format!("{}{}{}", DEBUG_TRACE_TRANSACTION_KEY_PREFIX, tx_hash, tracing_options_key(opts))
+}
+
+pub fn tracing_call_options_key(opts: Option<&GethDebugTracingCallOptions>) -> String {
+    let mut opts = opts.cloned().unwrap_or_default();
+    let tracing_options = std::mem::take(&mut opts.tracing_options);
+    let state_overrides = opts.state_overrides.take();
+    format!("{}{}{:?}", tracing_options_key(Some(&tracing_options)), state_override_key(state_overrides.as_ref()), opts)
+}
+
+/// Sorted, so the key does not depend on the iteration order of the set
+pub fn trace_types_key(trace_types: &HashSet<TraceType>) -> String {
+    let mut trace_types: Vec<String> = trace_types.iter().map(|trace_type| format!("{:?}", trace_type)).collect();
+    trace_types.sort();
+    trace_types.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracing_options(opts: serde_json::Value) -> GethDebugTracingOptions {
+        serde_json::from_value(opts).unwrap()
+    }
+
+    #[test]
+    fn ignores_the_timeout_and_empty_tracer_configs() {
+        let call_tracer = tracing_options(serde_json::json!({ "tracer": "callTracer" }));
+        let with_timeout = tracing_options(serde_json::json!({ "tracer": "callTracer", "timeout": "10s", "tracerConfig": {} }));
+        assert_eq!(tracing_options_key(Some(&call_tracer)), tracing_options_key(Some(&with_timeout)));
+        assert_eq!(tracing_options_key(None), tracing_options_key(Some(&GethDebugTracingOptions::default())));
+    }
+
+    #[test]
+    fn keys_differ_by_tracer_and_tracer_config() {
+        let prestate = tracing_options(serde_json::json!({ "tracer": "prestateTracer" }));
+        let diff_mode = tracing_options(serde_json::json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }));
+        let call_tracer = tracing_options(serde_json::json!({ "tracer": "callTracer" }));
+        assert_ne!(tracing_options_key(Some(&prestate)), tracing_options_key(Some(&diff_mode)));
+        assert_ne!(tracing_options_key(Some(&prestate)), tracing_options_key(Some(&call_tracer)));
+        assert_ne!(tracing_options_key(None), tracing_options_key(Some(&call_tracer)));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::api::core::{
+    BLOB_BASE_FEE_SEQUENCE, BLOCK_NUMBER_SEQUENCE, CALL_MANY_KEY_PREFIX, FEE_HISTORY_KEY_PREFIX, GAS_PRICE_SEQUENCE,
+    GET_BALANCE_KEY_PREFIX, MAX_PRIORITY_FEE_PER_GAS_SEQUENCE, SIMULATE_V1_KEY_PREFIX, TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX,
+    TRANSACTION_COUNT_KEY_PREFIX,
+};
+use crate::api::debug::{
+    DEBUG_ACCOUNT_RANGE_KEY_PREFIX, DEBUG_EXECUTION_WITNESS_KEY_PREFIX, DEBUG_RAW_BLOCK_KEY_PREFIX, DEBUG_RAW_HEADER_KEY_PREFIX,
+    DEBUG_RAW_RECEIPTS_KEY_PREFIX, DEBUG_RAW_TRANSACTIONS_KEY_PREFIX, DEBUG_STORAGE_RANGE_AT_KEY_PREFIX,
+    DEBUG_TRACE_BLOCK_BY_NUMBER_KEY_PREFIX, DEBUG_TRACE_CALL_KEY_PREFIX, DEBUG_TRACE_CALL_MANY_KEY_PREFIX,
+};
+use crate::api::otterscan::{
+    OTS_GET_BLOCK_DETAILS_BY_HASH_KEY_PREFIX, OTS_GET_BLOCK_DETAILS_KEY_PREFIX, OTS_GET_BLOCK_TRANSACTIONS_KEY_PREFIX,
+    OTS_GET_CONTRACT_CREATOR_KEY_PREFIX, OTS_GET_INTERNAL_OPERATIONS_KEY_PREFIX, OTS_GET_TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX,
+    OTS_GET_TRANSACTION_ERROR_KEY_PREFIX, OTS_HAS_CODE_KEY_PREFIX, OTS_SEARCH_TRANSACTIONS_AFTER_KEY_PREFIX,
+    OTS_SEARCH_TRANSACTIONS_BEFORE_KEY_PREFIX, OTS_TRACE_TRANSACTION_KEY_PREFIX,
+};
+use crate::api::trace::{
+    TRACE_BLOCK_KEY_PREFIX, TRACE_CALL_KEY_PREFIX, TRACE_FILTER_KEY_PREFIX, TRACE_REPLAY_BLOCK_TRANSACTIONS_KEY_PREFIX,
+    TRACE_REPLAY_TRANSACTION_KEY_PREFIX, TRACE_TRANSACTION_KEY_PREFIX,
+};
+use crate::helper::base::{CHAIN_ID_KEY, CLIENT_VERSION_KEY};
+use crate::helper::block::{
+    BLOCK_HASH_BY_NUMBER_KEY_PREFIX, BLOCK_KEY_PREFIX, RAW_TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX,
+    RAW_TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX, SENDER_NONCE_KEY_PREFIX, TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX,
+    TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX, TRANSACTION_BY_HASH_KEY_PREFIX, UNCLE_KEY_PREFIX,
+};
+use crate::helper::code::{CODE_BY_HASH_KEY_PREFIX, CODE_KEY_PREFIX};
+use crate::helper::derive::{GET_ACCOUNT_KEY_PREFIX, GET_PROOF_KEY_PREFIX, PROOF_INDEX_KEY_PREFIX};
+use crate::helper::finality::BLOCK_ENTRY_KEY_PREFIX;
+use crate::helper::sequence::sequence_key_prefix;
+use crate::helper::state::{
+    STATE_CHANGES_KEY_PREFIX, STATE_VALUES_BALANCE_KEY_PREFIX, STATE_VALUES_CODE_KEY_PREFIX, STATE_VALUES_NONCE_KEY_PREFIX,
+    STATE_VALUES_STORAGE_KEY_PREFIX, STORAGE_AT_KEY_PREFIX,
+};
+use crate::helper::trace::DEBUG_TRACE_TRANSACTION_KEY_PREFIX;
+use node_cache_recorder::KeyTtl;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Key prefixes of the entries recorded for each RPC method, entries derived from a response expire with it
+fn method_prefixes() -> Vec<(&'static str, Vec<String>)> {
+    let methods: Vec<(&str, Vec<&str>)> = vec![
+        ("eth_chainId", vec![CHAIN_ID_KEY]),
+        ("web3_clientVersion", vec![CLIENT_VERSION_KEY]),
+        ("eth_feeHistory", vec![FEE_HISTORY_KEY_PREFIX]),
+        // blocks are stored once by hash for both methods
+        ("eth_getBlockByHash", vec![BLOCK_KEY_PREFIX, BLOCK_HASH_BY_NUMBER_KEY_PREFIX]),
+        ("eth_getBlockByNumber", vec![BLOCK_KEY_PREFIX, BLOCK_HASH_BY_NUMBER_KEY_PREFIX]),
+        ("eth_getUncleByBlockHashAndIndex", vec![UNCLE_KEY_PREFIX]),
+        ("eth_getUncleByBlockNumberAndIndex", vec![UNCLE_KEY_PREFIX]),
+        ("eth_getTransactionByHash", vec![TRANSACTION_BY_HASH_KEY_PREFIX]),
+        ("eth_getTransactionByBlockHashAndIndex", vec![TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX]),
+        ("eth_getTransactionByBlockNumberAndIndex", vec![TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX]),
+        ("eth_getRawTransactionByBlockHashAndIndex", vec![RAW_TRANSACTION_BY_BLOCK_HASH_AND_INDEX_KEY_PREFIX]),
+        ("eth_getRawTransactionByBlockNumberAndIndex", vec![RAW_TRANSACTION_BY_BLOCK_NUMBER_AND_INDEX_KEY_PREFIX]),
+        ("eth_getTransactionBySenderAndNonce", vec![TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX, SENDER_NONCE_KEY_PREFIX]),
+        ("eth_getBalance", vec![GET_BALANCE_KEY_PREFIX, STATE_VALUES_BALANCE_KEY_PREFIX]),
+        ("eth_getStorageAt", vec![STORAGE_AT_KEY_PREFIX, STATE_VALUES_STORAGE_KEY_PREFIX]),
+        ("eth_getTransactionCount", vec![TRANSACTION_COUNT_KEY_PREFIX, STATE_VALUES_NONCE_KEY_PREFIX]),
+        ("eth_getCode", vec![CODE_KEY_PREFIX, CODE_BY_HASH_KEY_PREFIX, STATE_VALUES_CODE_KEY_PREFIX]),
+        ("eth_getAccount", vec![GET_ACCOUNT_KEY_PREFIX]),
+        ("eth_getProof", vec![GET_PROOF_KEY_PREFIX, PROOF_INDEX_KEY_PREFIX]),
+        ("eth_simulateV1", vec![SIMULATE_V1_KEY_PREFIX]),
+        ("eth_callMany", vec![CALL_MANY_KEY_PREFIX]),
+        ("debug_getRawHeader", vec![DEBUG_RAW_HEADER_KEY_PREFIX]),
+        ("debug_getRawBlock", vec![DEBUG_RAW_BLOCK_KEY_PREFIX]),
+        ("debug_getRawTransactions", vec![DEBUG_RAW_TRANSACTIONS_KEY_PREFIX]),
+        ("debug_getRawReceipts", vec![DEBUG_RAW_RECEIPTS_KEY_PREFIX]),
+        ("debug_storageRangeAt", vec![DEBUG_STORAGE_RANGE_AT_KEY_PREFIX]),
+        ("debug_accountRange", vec![DEBUG_ACCOUNT_RANGE_KEY_PREFIX]),
+        // state diffs are derived from prestate traces of blocks
+        ("debug_traceBlockByNumber", vec![DEBUG_TRACE_BLOCK_BY_NUMBER_KEY_PREFIX, STATE_CHANGES_KEY_PREFIX]),
+        ("debug_traceTransaction", vec![DEBUG_TRACE_TRANSACTION_KEY_PREFIX]),
+        ("debug_traceCall", vec![DEBUG_TRACE_CALL_KEY_PREFIX]),
+        ("debug_traceCallMany", vec![DEBUG_TRACE_CALL_MANY_KEY_PREFIX]),
+        ("debug_executionWitness", vec![DEBUG_EXECUTION_WITNESS_KEY_PREFIX]),
+        ("trace_call", vec![TRACE_CALL_KEY_PREFIX]),
+        ("trace_block", vec![TRACE_BLOCK_KEY_PREFIX]),
+        ("trace_filter", vec![TRACE_FILTER_KEY_PREFIX]),
+        ("trace_replayBlockTransactions", vec![TRACE_REPLAY_BLOCK_TRANSACTIONS_KEY_PREFIX]),
+        ("trace_replayTransaction", vec![TRACE_REPLAY_TRANSACTION_KEY_PREFIX]),
+        ("trace_transaction", vec![TRACE_TRANSACTION_KEY_PREFIX]),
+        ("ots_hasCode", vec![OTS_HAS_CODE_KEY_PREFIX]),
+        ("ots_getInternalOperations", vec![OTS_GET_INTERNAL_OPERATIONS_KEY_PREFIX]),
+        ("ots_getTransactionError", vec![OTS_GET_TRANSACTION_ERROR_KEY_PREFIX]),
+        ("ots_traceTransaction", vec![OTS_TRACE_TRANSACTION_KEY_PREFIX]),
+        ("ots_getBlockDetails", vec![OTS_GET_BLOCK_DETAILS_KEY_PREFIX]),
+        ("ots_getBlockDetailsByHash", vec![OTS_GET_BLOCK_DETAILS_BY_HASH_KEY_PREFIX]),
+        ("ots_getBlockTransactions", vec![OTS_GET_BLOCK_TRANSACTIONS_KEY_PREFIX]),
+        ("ots_searchTransactionsBefore", vec![OTS_SEARCH_TRANSACTIONS_BEFORE_KEY_PREFIX]),
+        ("ots_searchTransactionsAfter", vec![OTS_SEARCH_TRANSACTIONS_AFTER_KEY_PREFIX]),
+        ("ots_getTransactionBySenderAndNonce", vec![OTS_GET_TRANSACTION_BY_SENDER_AND_NONCE_KEY_PREFIX]),
+        ("ots_getContractCreator", vec![OTS_GET_CONTRACT_CREATOR_KEY_PREFIX]),
+    ];
+    let sequences = [
+        ("eth_blockNumber", BLOCK_NUMBER_SEQUENCE),
+        ("eth_gasPrice", GAS_PRICE_SEQUENCE),
+        ("eth_maxPriorityFeePerGas", MAX_PRIORITY_FEE_PER_GAS_SEQUENCE),
+        ("eth_blobBaseFee", BLOB_BASE_FEE_SEQUENCE),
+    ];
+    methods
+        .into_iter()
+        .map(|(method, prefixes)| (method, prefixes.into_iter().map(str::to_string).collect()))
+        .chain(sequences.into_iter().map(|(method, sequence)| (method, vec![sequence_key_prefix(sequence)])))
+        .collect()
+}
+
+/// Entries kept for the cache itself, they do not belong to any method and never expire
+const INTERNAL_KEY_PREFIXES: [&str; 1] = [BLOCK_ENTRY_KEY_PREFIX];
+
+/// Key prefixes of all methods, the longest one matching a key decides which method the entry belongs to
+pub fn method_key_prefixes() -> Vec<String> {
+    let mut prefixes: Vec<String> = method_prefixes()
+        .into_iter()
+        .flat_map(|(_, prefixes)| prefixes)
+        .chain(INTERNAL_KEY_PREFIXES.iter().map(|prefix| prefix.to_string()))
+        .collect();
+    prefixes.sort();
+    prefixes.dedup();
+    prefixes
+}
+
+/// Time-to-live of all entries recorded for an RPC method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodTtl {
+    pub method: String,
+    pub ttl: Duration,
+}
+
+impl MethodTtl {
+    pub fn key_ttls(&self) -> Vec<KeyTtl> {
+        let prefixes =
+            method_prefixes().into_iter().find(|(method, _)| *method == self.method).map(|(_, prefixes)| prefixes).unwrap_or_default();
+        prefixes.into_iter().map(|prefix| KeyTtl { prefix, ttl: self.ttl }).collect()
+    }
+}
+
+impl FromStr for MethodTtl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((method, seconds)) = s.split_once('=') else {
+            return Err(format!("Invalid ttl: {}, expected <method>=<seconds>", s));
+        };
+        if !method_prefixes().iter().any(|(known, _)| *known == method) {
+            return Err(format!("Unknown method for ttl: {}, node cache does not record it", method));
+        }
+        let seconds: u64 = seconds.parse().map_err(|_| format!("Invalid ttl seconds: {}", seconds))?;
+        Ok(MethodTtl { method: method.to_string(), ttl: Duration::from_secs(seconds) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_method_ttl() {
+        let ttl: MethodTtl = "eth_getBalance=60".parse().unwrap();
+        assert_eq!(ttl, MethodTtl { method: "eth_getBalance".to_string(), ttl: Duration::from_secs(60) });
+        assert!("eth_getBalance".parse::<MethodTtl>().is_err());
+        assert!("eth_getBalance=soon".parse::<MethodTtl>().is_err());
+        assert!("get_balance=60".parse::<MethodTtl>().is_err());
+    }
+
+    #[test]
+    fn maps_methods_to_key_prefixes() {
+        let ttl: MethodTtl = "eth_getCode=10".parse().unwrap();
+        let prefixes: Vec<String> = ttl.key_ttls().into_iter().map(|key_ttl| key_ttl.prefix).collect();
+        assert_eq!(prefixes, vec!["get_code", "code_by_hash", "state_values_code"]);
+    }
+
+    #[test]
+    fn dedups_key_prefixes() {
+        let prefixes = method_key_prefixes();
+        assert!(prefixes.contains(&"transaction_by_hash".to_string()));
+        assert!(prefixes.contains(&"block_hash_by_number".to_string()));
+        assert_eq!(prefixes.iter().filter(|prefix| *prefix == "block_by_hash").count(), 1);
+        assert!(prefixes.contains(&"state_changes".to_string()));
+        assert!(prefixes.contains(&"block_entry".to_string()));
+    }
+
+    #[test]
+    fn maps_sequences_to_their_keys() {
+        let ttl: MethodTtl = "eth_gasPrice=10".parse().unwrap();
+        let prefixes: Vec<String> = ttl.key_ttls().into_iter().map(|key_ttl| key_ttl.prefix).collect();
+        assert_eq!(prefixes, vec!["sequencegas_price:"]);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::helper::base::ApiInner;
+use crate::helper::block::{is_fixed_block, BlockKey};
+use crate::helper::error::alloy_error;
+use alloy_consensus::proofs::calculate_transaction_root;
+use alloy_consensus::EMPTY_ROOT_HASH;
+use alloy_primitives::{keccak256, Address, Bytes, B256, KECCAK256_EMPTY};
+use alloy_provider::network::eip2718::Encodable2718;
+use alloy_provider::network::{Ethereum, TransactionResponse};
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::{Account, Block, BlockId, EIP1186AccountProofResponse, Header, Transaction};
+use alloy_serde::JsonStorageKey;
+use alloy_transport::Transport;
+use alloy_trie::proof::verify_proof;
+use alloy_trie::root::ordered_trie_root_with_encoder;
+use alloy_trie::Nibbles;
+use jsonrpsee::core::RpcResult;
+use node_cache_recorder::Recorder;
+use tracing::error;
+
+/// Root of a trie with the encoded items in block order, as used for transactions and receipts
+fn ordered_root(items: &[Bytes]) -> B256 {
+    ordered_trie_root_with_encoder(items, |item, buf| buf.extend_from_slice(item))
+}
+
+/// Reason why the header does not hash to the block hash or the full transactions do not match the transactions root
+fn block_mismatch(block: &Block) -> Option<String> {
+    if block.header.inner.hash_slow() != block.header.hash {
+        return Some(format!("header of block {} does not hash to {}", block.header.number, block.header.hash));
+    }
+    if let Some(transactions) = block.transactions.as_transactions() {
+        let envelopes: Vec<_> = transactions.iter().map(|transaction| transaction.inner.clone()).collect();
+        if calculate_transaction_root(&envelopes) != block.header.transactions_root {
+            return Some(format!("transactions of block {} do not match the transactions root", block.header.hash));
+        }
+    }
+    None
+}
+
+/// Account and storage values of the proof are part of the state root
+fn proof_matches(state_root: B256, proof: &EIP1186AccountProofResponse) -> bool {
+    let account = Account { nonce: proof.nonce, balance: proof.balance, storage_root: proof.storage_hash, code_hash: proof.code_hash };
+    let key = Nibbles::unpack(keccak256(proof.address));
+    let exists = verify_proof(state_root, key.clone(), Some(alloy_rlp::encode(account)), &proof.account_proof).is_ok();
+    // nodes return zero values for accounts which are not part of the state
+    let is_empty = proof.nonce == 0
+        && proof.balance.is_zero()
+        && (proof.code_hash == KECCAK256_EMPTY || proof.code_hash.is_zero())
+        && (proof.storage_hash == EMPTY_ROOT_HASH || proof.storage_hash.is_zero());
+    if !exists && !(is_empty && verify_proof(state_root, key, None, &proof.account_proof).is_ok()) {
+        return false;
+    }
+    proof.storage_proof.iter().all(|storage_proof| {
+        let value = (!storage_proof.value.is_zero()).then(|| alloy_rlp::encode(storage_proof.value));
+        verify_proof(proof.storage_hash, Nibbles::unpack(keccak256(storage_proof.key.as_b256())), value, &storage_proof.proof).is_ok()
+    })
+}
+
+impl<T, P, R> ApiInner<T, P, R>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + Send + Sync + Clone + 'static,
+    R: Recorder + Sync + Send + 'static,
+{
+    /// Count the failed verification, the response is not recorded
+    fn report_misbehaviour(&self, what: &str, reason: &str) {
+        let misbehaviours = self.config.upstream_health.report_misbehaviour();
+        error!("Upstream is misbehaving ({} times so far), not recording {}: {}", misbehaviours, what, reason);
+    }
+
+    /// Header hash and, for full blocks, the transactions root match the block
+    pub fn verify_block(&self, block: &Block) -> bool {
+        if !self.config.verify {
+            return true;
+        }
+        match block_mismatch(block) {
+            Some(reason) => {
+                self.report_misbehaviour("block", &reason);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// The uncle header hashes to the uncle hash of the including block
+    pub fn verify_uncle(&self, uncle: &Block, uncle_hash: B256) -> bool {
+        if !self.config.verify {
+            return true;
+        }
+        if uncle.header.inner.hash_slow() != uncle_hash {
+            self.report_misbehaviour("uncle", &format!("uncle header does not hash to {}", uncle_hash));
+            return false;
+        }
+        true
+    }
+
+    /// The transaction is part of a verified full block at its index, pending transactions can not be verified
+    pub async fn verify_transaction(&self, transaction: &Transaction) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let (Some(block_hash), Some(index)) = (transaction.block_hash, transaction.transaction_index) else {
+            return Ok(false);
+        };
+        let Some(block) = self.lookup_block(BlockKey::Hash(block_hash), true).await? else {
+            return Ok(false);
+        };
+        // a fetched block which fails verification was already reported
+        if block_mismatch(&block).is_some() {
+            return Ok(false);
+        }
+        let Some(expected) = block.transactions.as_transactions().and_then(|transactions| transactions.get(index as usize)) else {
+            self.report_misbehaviour("transaction", &format!("block {} has no transaction at index {}", block_hash, index));
+            return Ok(false);
+        };
+        let encoded = transaction.inner.encoded_2718();
+        if encoded != expected.inner.encoded_2718() || keccak256(&encoded) != transaction.tx_hash() {
+            self.report_misbehaviour(
+                "transaction",
+                &format!("transaction {} differs from index {} of block {}", transaction.tx_hash(), index, block_hash),
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// The raw transaction is the transaction at the index of a verified full block
+    pub async fn verify_raw_transaction(&self, block: BlockKey, index: usize, raw: &Bytes) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let Some(block) = self.lookup_block(block, true).await? else {
+            return Ok(false);
+        };
+        // a fetched block which fails verification was already reported
+        if block_mismatch(&block).is_some() {
+            return Ok(false);
+        }
+        let Some(expected) = block.transactions.as_transactions().and_then(|transactions| transactions.get(index)) else {
+            self.report_misbehaviour("raw transaction", &format!("block {} has no transaction at index {}", block.header.hash, index));
+            return Ok(false);
+        };
+        if keccak256(raw) != expected.tx_hash() || raw[..] != expected.inner.encoded_2718()[..] {
+            self.report_misbehaviour(
+                "raw transaction",
+                &format!("raw transaction differs from {} at index {} of block {}", expected.tx_hash(), index, block.header.hash),
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Verified header of a fixed block, responses for moving tags can not be verified and are not recorded under `--verify`
+    async fn verification_header(&self, block: BlockId) -> RpcResult<Option<Header>> {
+        if !is_fixed_block(block) {
+            return Ok(None);
+        }
+        Ok(self.lookup_block(block.into(), false).await?.map(|block| block.header))
+    }
+
+    pub async fn verify_raw_header(&self, block: BlockId, raw_header: &Bytes) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let Some(header) = self.verification_header(block).await? else {
+            return Ok(false);
+        };
+        if keccak256(raw_header) != header.hash {
+            self.report_misbehaviour("debug_getRawHeader", &format!("raw header does not hash to {}", header.hash));
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    pub async fn verify_raw_transactions(&self, block: BlockId, raw_transactions: &[Bytes]) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let Some(header) = self.verification_header(block).await? else {
+            return Ok(false);
+        };
+        if ordered_root(raw_transactions) != header.transactions_root {
+            self.report_misbehaviour("debug_getRawTransactions", &format!("transactions root mismatch in block {}", header.hash));
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    pub async fn verify_raw_receipts(&self, block: BlockId, raw_receipts: &[Bytes]) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let Some(header) = self.verification_header(block).await? else {
+            return Ok(false);
+        };
+        if ordered_root(raw_receipts) != header.receipts_root {
+            self.report_misbehaviour("debug_getRawReceipts", &format!("receipts root mismatch in block {}", header.hash));
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Account and storage proofs lead to the state root of the block
+    pub async fn verify_proof(&self, proof: &EIP1186AccountProofResponse, block: Option<BlockId>) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let Some(header) = self.verification_header(block.unwrap_or(BlockId::latest())).await? else {
+            return Ok(false);
+        };
+        if !proof_matches(header.state_root, proof) {
+            self.report_misbehaviour(
+                "eth_getProof",
+                &format!("proof of {} does not match the state root of block {}", proof.address, header.hash),
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Compare a state value of the account with a proof against the state root of the block
+    pub async fn verify_account(
+        &self,
+        method: &str,
+        address: Address,
+        slot: Option<B256>,
+        block: Option<BlockId>,
+        matches: impl FnOnce(&EIP1186AccountProofResponse) -> bool,
+    ) -> RpcResult<bool> {
+        if !self.config.verify {
+            return Ok(true);
+        }
+        let block = block.unwrap_or(BlockId::latest());
+        let (Some(header), Some(provider)) = (self.verification_header(block).await?, self.provider.as_ref()) else {
+            return Ok(false);
+        };
+        let keys: Vec<JsonStorageKey> = slot.into_iter().map(JsonStorageKey::from).collect();
+        let proof: EIP1186AccountProofResponse =
+            provider.raw_request("eth_getProof".into(), (address, keys, block)).await.map_err(alloy_error)?;
+        if !proof_matches(header.state_root, &proof) {
+            self.report_misbehaviour(method, &format!("proof of {} does not match the state root of block {}", address, header.hash));
+            return Ok(false);
+        }
+        if !matches(&proof) {
+            self.report_misbehaviour(method, &format!("value of {} differs from the proven value in block {}", address, header.hash));
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+    use alloy_rpc_types_eth::BlockTransactions;
+    use alloy_trie::proof::ProofRetainer;
+    use alloy_trie::HashBuilder;
+
+    fn block(transactions_root: B256) -> Block {
+        let inner = alloy_consensus::Header { number: 1, transactions_root, ..Default::default() };
+        Block {
+            header: Header { hash: inner.hash_slow(), inner, ..Default::default() },
+            transactions: BlockTransactions::Full(vec![]),
+            ..Default::default()
+        }
+    }
+
+    fn account(balance: u64) -> EIP1186AccountProofResponse {
+        EIP1186AccountProofResponse {
+            address: Address::repeat_byte(1),
+            balance: U256::from(balance),
+            code_hash: KECCAK256_EMPTY,
+            nonce: 1,
+            storage_hash: EMPTY_ROOT_HASH,
+            ..Default::default()
+        }
+    }
+
+    /// State root of a state with the account as its only leaf, and the proof of the account
+    fn single_account_state(proof: &EIP1186AccountProofResponse) -> (B256, Vec<Bytes>) {
+        let account = Account { nonce: proof.nonce, balance: proof.balance, storage_root: proof.storage_hash, code_hash: proof.code_hash };
+        let key = Nibbles::unpack(keccak256(proof.address));
+        let mut builder = HashBuilder::default().with_proof_retainer(ProofRetainer::new(vec![key.clone()]));
+        builder.add_leaf(key.clone(), &alloy_rlp::encode(account));
+        let state_root = builder.root();
+        (state_root, builder.take_proof_nodes().matching_nodes_sorted(&key).into_iter().map(|(_, node)| node).collect())
+    }
+
+    #[test]
+    fn block_matches_its_hash_and_transactions_root() {
+        assert_eq!(block_mismatch(&block(EMPTY_ROOT_HASH)), None);
+        assert!(block_mismatch(&block(B256::ZERO)).is_some());
+        let mut tampered = block(EMPTY_ROOT_HASH);
+        tampered.header.hash = B256::ZERO;
+        assert!(block_mismatch(&tampered).is_some());
+        // transaction hashes can not be checked against the root
+        let mut hashes = block(B256::ZERO);
+        hashes.transactions = BlockTransactions::Hashes(vec![B256::ZERO]);
+        assert_eq!(block_mismatch(&hashes), None);
+    }
+
+    #[test]
+    fn proof_matches_the_state_root() {
+        let mut proof = account(100);
+        let (state_root, account_proof) = single_account_state(&proof);
+        proof.account_proof = account_proof;
+        assert!(proof_matches(state_root, &proof));
+        proof.balance = U256::from(101);
+        assert!(!proof_matches(state_root, &proof));
+    }
+
+    #[test]
+    fn empty_accounts_are_proven_absent() {
+        let empty = EIP1186AccountProofResponse { address: Address::repeat_byte(2), ..Default::default() };
+        assert!(proof_matches(EMPTY_ROOT_HASH, &empty));
+        assert!(!proof_matches(EMPTY_ROOT_HASH, &account(100)));
+        assert!(!proof_matches(B256::repeat_byte(1), &empty));
+    }
+}
@@ -1,5 +1,17 @@
 mod api;
+mod config;
 mod helper;
 
 pub use api::core::NodeCacheEthApi;
 pub use api::debug::NodeCacheDebugApi;
+pub use api::debug_ext::{DebugExtApiServer, StorageEntry, StorageRangeResult};
+pub use api::net::NodeCacheNetApi;
+pub use api::otterscan::NodeCacheOtterscanApi;
+pub use api::trace::NodeCacheTraceApi;
+pub use api::web3::NodeCacheWeb3Api;
+pub use config::{Finality, IndexLock, NodeCacheConfig, NodeStatus, SubmissionPolicy, UpstreamHealth};
+pub use helper::base::{client_version, KEY_SCHEMA_VERSION};
+pub use helper::code::dedup_code;
+pub use helper::eviction::entry_link;
+pub use helper::migrate::{check_schema, migrate};
+pub use helper::ttl::{method_key_prefixes, MethodTtl};